@@ -0,0 +1,167 @@
+//! Local, content-addressed store for the files registered in `pallet_file_storage`.
+//!
+//! A blob is stored on disk under the hex-encoded BLAKE2-256 hash of its content, which must be
+//! the `cid` of a `Files` entry. Blobs are uploaded and downloaded through the `blob_put` and
+//! `blob_get` RPC methods. A background task removes the blobs whose `Files` entry is gone.
+
+use crate::service::FullClient;
+use futures::StreamExt;
+use node_template_runtime::{
+	opaque::Block, pallet_file_storage::File, AccountId, AssetId, Balance, Runtime,
+};
+use pallet_file_storage_runtime_api::FileStorageApi;
+use sc_client_api::BlockchainEvents;
+use sc_service::{error::Error as ServiceError, TaskManager};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{hashing::blake2_256, H256};
+use sp_runtime::{generic::BlockId, traits::Header as HeaderT};
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+/// Number of finalized blocks between two garbage collections.
+const GC_PERIOD: u32 = 100;
+
+const LOG_TARGET: &str = "blob-store";
+
+/// Blobs on disk, keyed by the hash of their content.
+pub struct BlobStore {
+	dir: PathBuf,
+}
+
+impl BlobStore {
+	/// Opens the store in `dir`, creating the directory if needed.
+	pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+		let dir = dir.into();
+		fs::create_dir_all(&dir)?;
+		Ok(Self { dir })
+	}
+
+	/// The hash a blob with `data` is stored under.
+	pub fn hash_of(data: &[u8]) -> H256 {
+		H256(blake2_256(data))
+	}
+
+	fn path(&self, hash: &H256) -> PathBuf {
+		self.dir.join(format!("{:x}", hash))
+	}
+
+	/// Stores `data` under its hash and returns the hash.
+	///
+	/// The blob is written to a temporary file first, so a blob is either complete or missing.
+	pub fn put(&self, data: &[u8]) -> io::Result<H256> {
+		let hash = Self::hash_of(data);
+		let path = self.path(&hash);
+		if !path.exists() {
+			let partial = path.with_extension("partial");
+			fs::write(&partial, data)?;
+			fs::rename(&partial, &path)?;
+		}
+		Ok(hash)
+	}
+
+	/// The blob stored under `hash`, if any.
+	pub fn get(&self, hash: &H256) -> io::Result<Option<Vec<u8>>> {
+		match fs::read(self.path(hash)) {
+			Ok(data) => Ok(Some(data)),
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+			Err(e) => Err(e),
+		}
+	}
+
+	/// Path of the blob stored under `hash`, if any, e.g. to stream it.
+	pub fn file_path(&self, hash: &H256) -> Option<PathBuf> {
+		Some(self.path(hash)).filter(|path| path.is_file())
+	}
+
+	/// Removes the blob stored under `hash`, if any.
+	pub fn remove(&self, hash: &H256) -> io::Result<()> {
+		match fs::remove_file(self.path(hash)) {
+			Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+			_ => Ok(()),
+		}
+	}
+
+	/// Hashes of all stored blobs.
+	pub fn hashes(&self) -> io::Result<Vec<H256>> {
+		let mut hashes = Vec::new();
+		for entry in fs::read_dir(&self.dir)? {
+			if let Some(hash) = parse_hash(&entry?.path()) {
+				hashes.push(hash);
+			}
+		}
+		Ok(hashes)
+	}
+}
+
+fn parse_hash(path: &Path) -> Option<H256> {
+	let bytes = sp_core::bytes::from_hex(path.file_name()?.to_str()?).ok()?;
+	(bytes.len() == 32).then(|| H256::from_slice(&bytes))
+}
+
+/// Removes, every [`GC_PERIOD`] finalized blocks, the blobs whose file is registered neither in
+/// the finalized nor in the best block, so files created or removed in blocks that may still be
+/// reverted keep their blob.
+pub async fn collect_garbage<C>(client: Arc<C>, store: Arc<BlobStore>)
+where
+	C: BlockchainEvents<Block> + HeaderBackend<Block> + ProvideRuntimeApi<Block>,
+	C::Api: FileStorageApi<Block, AccountId, File<Runtime>, AssetId, Balance>,
+{
+	let mut finality_notifications = client.finality_notification_stream();
+	let mut last_run = 0;
+
+	while let Some(notification) = finality_notifications.next().await {
+		let number = *notification.header.number();
+		if number < last_run + GC_PERIOD {
+			continue
+		}
+		last_run = number;
+
+		let hashes = match store.hashes() {
+			Ok(hashes) => hashes,
+			Err(e) => {
+				log::warn!(target: LOG_TARGET, "Failed to list blobs: {}", e);
+				continue
+			},
+		};
+
+		let finalized = BlockId::hash(notification.hash);
+		let best = BlockId::hash(client.info().best_hash);
+		let api = client.runtime_api();
+		for hash in hashes {
+			let registered = |at: &BlockId<Block>| {
+				api.file(at, hash).map(|file| file.is_some()).unwrap_or(true)
+			};
+			if registered(&finalized) || registered(&best) {
+				continue
+			}
+
+			match store.remove(&hash) {
+				Ok(()) => log::debug!(target: LOG_TARGET, "Removed blob {:?}", hash),
+				Err(e) => log::warn!(target: LOG_TARGET, "Failed to remove blob {:?}: {}", hash, e),
+			}
+		}
+	}
+}
+
+/// Opens the store in `dir` and spawns its garbage collection.
+pub fn spawn(
+	dir: PathBuf,
+	client: Arc<FullClient>,
+	task_manager: &TaskManager,
+) -> Result<Arc<BlobStore>, ServiceError> {
+	let store = BlobStore::open(&dir).map_err(|e| {
+		ServiceError::Other(format!("Failed to open blob store in {}: {}", dir.display(), e))
+	})?;
+	let store = Arc::new(store);
+
+	task_manager.spawn_handle().spawn(
+		"blob-store-gc",
+		None,
+		collect_garbage(client, store.clone()),
+	);
+	Ok(store)
+}
@@ -0,0 +1,159 @@
+//! The `export-poe-certificate` subcommand.
+//!
+//! Exports a [`PoeCertificate`] for a claim: the header of the latest finalized block that has a
+//! stored GRANDPA justification, the read proof of the claim's `Claims` entry in that block's
+//! state and the justification itself. The certificate can be checked fully offline with the
+//! `poe-verify` binary of the `poe-certificate` crate.
+
+use crate::service::FullClient;
+use poe_certificate::PoeCertificate;
+use sc_cli::{CliConfiguration, DatabaseParams, PruningParams, Result, SharedParams};
+use sc_client_api::{BlockBackend, ProofProvider, StorageProvider};
+use sp_blockchain::HeaderBackend;
+use sp_core::{storage::StorageKey, H256};
+use sp_finality_grandpa::GRANDPA_ENGINE_ID;
+use sp_runtime::{generic::BlockId, traits::Header as HeaderT};
+use std::{fs, path::PathBuf, sync::Arc};
+
+/// How many blocks to walk back from the finalized head looking for a stored justification.
+///
+/// GRANDPA stores a justification at least every `justification_period` (512) blocks.
+const MAX_JUSTIFICATION_SEARCH: u32 = 1024;
+
+fn parse_hash(s: &str) -> std::result::Result<H256, String> {
+	let bytes = sp_core::bytes::from_hex(s).map_err(|e| format!("{:?}", e))?;
+	if bytes.len() != 32 {
+		return Err("expected a 32 byte hash".into())
+	}
+	Ok(H256::from_slice(&bytes))
+}
+
+/// The `export-poe-certificate` command.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct ExportPoeCertificateCmd {
+	/// The claimed hash, as passed to `PoeModule::create_claim`.
+	#[clap(value_name = "CLAIM", parse(try_from_str = parse_hash))]
+	pub claim: H256,
+
+	/// Prove the claim against this finalized block instead of the latest justified one.
+	///
+	/// The block must have a stored GRANDPA justification.
+	#[clap(long, value_name = "HASH", parse(try_from_str = parse_hash))]
+	pub at: Option<H256>,
+
+	/// Write the certificate to this file instead of stdout.
+	#[clap(long, short, value_name = "PATH")]
+	pub output: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl ExportPoeCertificateCmd {
+	/// Run the command against the given client.
+	pub fn run(&self, client: Arc<FullClient>) -> Result<()> {
+		let (block_hash, justification) = match self.at {
+			Some(hash) => {
+				let justification = grandpa_justification(&client, hash)?.ok_or_else(|| {
+					format!("Block {:?} has no stored GRANDPA justification.", hash)
+				})?;
+				(hash, justification)
+			},
+			None => latest_justified_block(&client)?,
+		};
+
+		let header = client
+			.header(BlockId::Hash(block_hash))?
+			.ok_or_else(|| format!("Unknown block {:?}.", block_hash))?;
+		if *header.number() > client.info().finalized_number {
+			return Err(format!("Block {:?} is not finalized.", block_hash).into())
+		}
+
+		let key = poe_certificate::claim_storage_key(&self.claim);
+		let id = BlockId::Hash(block_hash);
+		if client.storage(&id, &StorageKey(key.clone()))?.is_none() {
+			return Err(format!(
+				"Claim {:?} does not exist at block #{}. Claims created after the latest \
+				justified block can be exported once the next justification is stored.",
+				self.claim,
+				header.number(),
+			)
+			.into())
+		}
+		let proof = client.read_proof(&id, &mut std::iter::once(key.as_slice())).map_err(|e| {
+			format!(
+				"Failed to prove the claim at block #{} ({}). Exporting certificates for older \
+				blocks requires a node running with `--state-pruning archive`.",
+				header.number(),
+				e,
+			)
+		})?;
+
+		let certificate = PoeCertificate {
+			header: header.clone(),
+			claim: self.claim,
+			proof: proof.into_iter_nodes().collect(),
+			justification,
+		};
+
+		match &self.output {
+			Some(path) => fs::write(path, certificate.to_hex())?,
+			None => println!("{}", certificate.to_hex()),
+		}
+		log::info!(
+			"Exported certificate for claim {:?} finalized at #{} ({:?})",
+			self.claim,
+			header.number(),
+			block_hash,
+		);
+
+		Ok(())
+	}
+}
+
+fn grandpa_justification(client: &FullClient, hash: H256) -> Result<Option<Vec<u8>>> {
+	Ok(client
+		.justifications(&BlockId::Hash(hash))?
+		.and_then(|justifications| justifications.into_justification(GRANDPA_ENGINE_ID)))
+}
+
+fn latest_justified_block(client: &FullClient) -> Result<(H256, Vec<u8>)> {
+	let mut hash = client.info().finalized_hash;
+	for _ in 0..MAX_JUSTIFICATION_SEARCH {
+		if let Some(justification) = grandpa_justification(client, hash)? {
+			return Ok((hash, justification))
+		}
+		let header = client
+			.header(BlockId::Hash(hash))?
+			.ok_or_else(|| format!("Unknown block {:?}.", hash))?;
+		if header.number == 0 {
+			break
+		}
+		hash = header.parent_hash;
+	}
+
+	Err("No finalized block with a stored GRANDPA justification found.".into())
+}
+
+impl CliConfiguration for ExportPoeCertificateCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
@@ -1,15 +1,24 @@
+use hex_literal::hex;
+#[cfg(feature = "dev-sudo")]
+use node_template_runtime::SudoConfig;
 use node_template_runtime::{
-	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, Signature, SudoConfig,
-	SystemConfig, WASM_BINARY,
+	opaque::SessionKeys, pallet_file_storage::GenesisFile, AccountId, AuraConfig, Balance,
+	BalancesConfig, CouncilConfig, FileStorageConfig, GenesisConfig, GrandpaConfig, Hash,
+	PoeModuleConfig, SessionConfig, Signature, SystemConfig, ValidatorSetConfig, SS58_PREFIX, UNIT,
+	WASM_BINARY,
 };
-use sc_service::ChainType;
+use sc_service::{ChainType, Properties};
+use sc_telemetry::TelemetryEndpoints;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
-use sp_core::{sr25519, Pair, Public};
+use sp_core::{crypto::UncheckedInto, sr25519, Pair, Public};
 use sp_finality_grandpa::AuthorityId as GrandpaId;
 use sp_runtime::traits::{IdentifyAccount, Verify};
 
 // The URL for the telemetry server.
-// const STAGING_TELEMETRY_URL: &str = "wss://telemetry.polkadot.io/submit/";
+const STAGING_TELEMETRY_URL: &str = "wss://telemetry.polkadot.io/submit/";
+
+/// Protocol ID of the staging network, keeping its peers apart from other networks.
+const STAGING_PROTOCOL_ID: &str = "poe-staging";
 
 /// Specialized `ChainSpec`. This is a specialization of the general Substrate ChainSpec type.
 pub type ChainSpec = sc_service::GenericChainSpec<GenesisConfig>;
@@ -31,9 +40,26 @@ where
 	AccountPublic::from(get_from_seed::<TPublic>(seed)).into_account()
 }
 
-/// Generate an Aura authority key.
-pub fn authority_keys_from_seed(s: &str) -> (AuraId, GrandpaId) {
-	(get_from_seed::<AuraId>(s), get_from_seed::<GrandpaId>(s))
+/// Generate a validator account with its Aura and GRANDPA keys.
+pub fn authority_keys_from_seed(s: &str) -> (AccountId, AuraId, GrandpaId) {
+	(
+		get_account_id_from_seed::<sr25519::Public>(s),
+		get_from_seed::<AuraId>(s),
+		get_from_seed::<GrandpaId>(s),
+	)
+}
+
+fn session_keys(aura: AuraId, grandpa: GrandpaId) -> SessionKeys {
+	SessionKeys { aura, grandpa }
+}
+
+/// Token symbol, decimals and address format shown by wallets.
+pub fn chain_properties() -> Properties {
+	let mut properties = Properties::new();
+	properties.insert("tokenSymbol".into(), "UNIT".into());
+	properties.insert("tokenDecimals".into(), 12.into());
+	properties.insert("ss58Format".into(), SS58_PREFIX.into());
+	properties
 }
 
 pub fn development_config() -> Result<ChainSpec, String> {
@@ -51,7 +77,9 @@ pub fn development_config() -> Result<ChainSpec, String> {
 				// Initial PoA authorities
 				vec![authority_keys_from_seed("Alice")],
 				// Sudo account
-				get_account_id_from_seed::<sr25519::Public>("Alice"),
+				Some(get_account_id_from_seed::<sr25519::Public>("Alice")),
+				// Council members
+				vec![get_account_id_from_seed::<sr25519::Public>("Alice")],
 				// Pre-funded accounts
 				vec![
 					get_account_id_from_seed::<sr25519::Public>("Alice"),
@@ -70,7 +98,7 @@ pub fn development_config() -> Result<ChainSpec, String> {
 		None,
 		None,
 		// Properties
-		None,
+		Some(chain_properties()),
 		// Extensions
 		None,
 	))
@@ -90,8 +118,13 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 				wasm_binary,
 				// Initial PoA authorities
 				vec![authority_keys_from_seed("Alice"), authority_keys_from_seed("Bob")],
-				// Sudo account
-				get_account_id_from_seed::<sr25519::Public>("Alice"),
+				// No sudo account: the chain is administered through governance
+				None,
+				// Council members
+				vec![
+					get_account_id_from_seed::<sr25519::Public>("Alice"),
+					get_account_id_from_seed::<sr25519::Public>("Bob"),
+				],
 				// Pre-funded accounts
 				vec![
 					get_account_id_from_seed::<sr25519::Public>("Alice"),
@@ -116,9 +149,72 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 		None,
 		// Protocol ID
 		None,
+		None,
 		// Properties
+		Some(chain_properties()),
+		// Extensions
 		None,
+	))
+}
+
+pub fn staging_testnet_config() -> Result<ChainSpec, String> {
+	let wasm_binary = WASM_BINARY.ok_or_else(|| "Staging wasm not available".to_string())?;
+
+	// Public keys of the two staging validators: account, Aura and GRANDPA. Their secret keys
+	// only live in the keystores of the nodes of the staging testnet's operators, so nobody else
+	// can author blocks on it. A network with validators of its own is described in a file for
+	// `generate-spec` instead, see `crate::generate_spec`.
+	let initial_authorities: Vec<(AccountId, AuraId, GrandpaId)> = vec![
+		(
+			hex!["0babe9efb1e767d1f6dac06287e406f3e82b1794e59180143075f17d83032088"].into(),
+			hex!["5fbde1ea2bb5cb2dbb6f28e11b31e42dc5580103403acffca157b887e8f89c5c"]
+				.unchecked_into(),
+			hex!["83639f5a66e5867d77b80daf3bc97cbf22f23f90056004704dac7ef4fd8d1663"]
+				.unchecked_into(),
+		),
+		(
+			hex!["7d63024b189cd52c36a740b1ff1dbb40d3b47841479848c62d9758fdfbbc8835"].into(),
+			hex!["4e7b9a4ab6721713930809a57f59f063bdda34b6d2633a78dbdf855495c82a13"]
+				.unchecked_into(),
+			hex!["576a1bd0ffcfad677bdd54beccf6522905743d106e801a0fac685795656d6e07"]
+				.unchecked_into(),
+		),
+	];
+
+	Ok(ChainSpec::from_genesis(
+		// Name
+		"Staging Testnet",
+		// ID
+		"staging_testnet",
+		ChainType::Live,
+		move || {
+			let validators: Vec<AccountId> =
+				initial_authorities.iter().map(|x| x.0.clone()).collect();
+			genesis(
+				wasm_binary,
+				initial_authorities.clone(),
+				// No sudo key, the staging testnet is governed by its council.
+				None,
+				// Council members
+				validators.clone(),
+				// Enough for the validators to pay fees.
+				validators.into_iter().map(|k| (k, 1_000 * UNIT)).collect(),
+				vec![],
+				vec![],
+			)
+		},
+		// Bootnodes
+		vec![],
+		// Telemetry
+		Some(
+			TelemetryEndpoints::new(vec![(STAGING_TELEMETRY_URL.to_string(), 0)])
+				.expect("Staging telemetry url is valid; qed"),
+		),
+		// Protocol ID
+		Some(STAGING_PROTOCOL_ID),
 		None,
+		// Properties
+		Some(chain_properties()),
 		// Extensions
 		None,
 	))
@@ -127,30 +223,71 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 /// Configure initial storage state for FRAME modules.
 fn testnet_genesis(
 	wasm_binary: &[u8],
-	initial_authorities: Vec<(AuraId, GrandpaId)>,
-	root_key: AccountId,
+	initial_authorities: Vec<(AccountId, AuraId, GrandpaId)>,
+	root_key: Option<AccountId>,
+	council_members: Vec<AccountId>,
 	endowed_accounts: Vec<AccountId>,
 	_enable_println: bool,
 ) -> GenesisConfig {
+	genesis(
+		wasm_binary,
+		initial_authorities,
+		root_key,
+		council_members,
+		// Configure endowed accounts with initial balance of 1 << 60.
+		endowed_accounts.into_iter().map(|k| (k, 1 << 60)).collect(),
+		vec![],
+		vec![],
+	)
+}
+
+/// A file registered in the genesis block.
+pub type InitialFile = GenesisFile<AccountId, Hash, Balance>;
+
+/// Configure initial storage state for FRAME modules from explicit balances, claims and files.
+///
+/// `root_key` is only used by runtimes built with the `dev-sudo` feature.
+pub fn genesis(
+	wasm_binary: &[u8],
+	initial_authorities: Vec<(AccountId, AuraId, GrandpaId)>,
+	root_key: Option<AccountId>,
+	council_members: Vec<AccountId>,
+	balances: Vec<(AccountId, Balance)>,
+	claims: Vec<(Hash, AccountId)>,
+	files: Vec<InitialFile>,
+) -> GenesisConfig {
+	#[cfg(not(feature = "dev-sudo"))]
+	let _ = root_key;
+
 	GenesisConfig {
 		system: SystemConfig {
 			// Add Wasm runtime to storage.
 			code: wasm_binary.to_vec(),
 		},
-		balances: BalancesConfig {
-			// Configure endowed accounts with initial balance of 1 << 60.
-			balances: endowed_accounts.iter().cloned().map(|k| (k, 1 << 60)).collect(),
-		},
-		aura: AuraConfig {
-			authorities: initial_authorities.iter().map(|x| (x.0.clone())).collect(),
+		balances: BalancesConfig { balances },
+		validator_set: ValidatorSetConfig {
+			initial_validators: initial_authorities.iter().map(|x| x.0.clone()).collect(),
 		},
-		grandpa: GrandpaConfig {
-			authorities: initial_authorities.iter().map(|x| (x.1.clone(), 1)).collect(),
+		session: SessionConfig {
+			keys: initial_authorities
+				.iter()
+				.map(|x| (x.0.clone(), x.0.clone(), session_keys(x.1.clone(), x.2.clone())))
+				.collect(),
 		},
+		// Aura and GRANDPA authorities are set up by `pallet_session` from the keys above.
+		aura: AuraConfig { authorities: vec![] },
+		grandpa: GrandpaConfig { authorities: vec![] },
+		#[cfg(feature = "dev-sudo")]
 		sudo: SudoConfig {
 			// Assign network admin rights.
-			key: Some(root_key),
+			key: root_key,
 		},
 		transaction_payment: Default::default(),
+		assets: Default::default(),
+		council: CouncilConfig { members: council_members, phantom: Default::default() },
+		democracy: Default::default(),
+		treasury: Default::default(),
+		poe_module: PoeModuleConfig { claims },
+		file_storage: FileStorageConfig { files },
 	}
 }
@@ -1,4 +1,6 @@
+use crate::service::Sealing;
 use sc_cli::RunCmd;
+use std::{net::SocketAddr, path::PathBuf};
 
 #[derive(Debug, clap::Parser)]
 pub struct Cli {
@@ -7,6 +9,35 @@ pub struct Cli {
 
 	#[clap(flatten)]
 	pub run: RunCmd,
+
+	/// Seal blocks without Aura and GRANDPA, in development mode only.
+	///
+	/// `instant` seals a block as soon as a transaction enters the pool, `manual` only on
+	/// `engine_createBlock` calls and `interval=N` every N milliseconds. Blocks can be sealed
+	/// and finalized through `engine_createBlock` and `engine_finalizeBlock` in every mode.
+	#[clap(long, value_name = "instant|manual|interval=N")]
+	pub sealing: Option<Sealing>,
+
+	/// Store the contents of registered files in PATH and serve them through the `blob_put`
+	/// and `blob_get` RPC methods.
+	#[clap(long, value_name = "PATH")]
+	pub blob_store: Option<PathBuf>,
+
+	/// Serve the contents of registered files over HTTP on ADDR, e.g. `127.0.0.1:8080`.
+	///
+	/// Requests must be signed by an account that may download the file. Needs `--blob-store`.
+	#[clap(long, value_name = "ADDR")]
+	pub file_gateway: Option<SocketAddr>,
+
+	/// Index the events of the `FileStorage`, `PoeModule` and `Balances` pallets in the SQLite
+	/// database at PATH and serve them through the `indexer_*` RPC methods.
+	#[clap(long, value_name = "PATH")]
+	pub indexer: Option<PathBuf>,
+
+	/// File holding the token of the remote signer at an `http://` `--keystore-uri`, the file
+	/// the signer was started with as `--http-token-file`.
+	#[clap(long, value_name = "PATH")]
+	pub keystore_token_file: Option<PathBuf>,
 }
 
 #[derive(Debug, clap::Subcommand)]
@@ -18,6 +49,9 @@ pub enum Subcommand {
 	/// Build a chain specification.
 	BuildSpec(sc_cli::BuildSpecCmd),
 
+	/// Generate a chain specification from a TOML or JSON network description.
+	GenerateSpec(crate::generate_spec::GenerateSpecCmd),
+
 	/// Validate blocks.
 	CheckBlock(sc_cli::CheckBlockCmd),
 
@@ -50,4 +84,13 @@ pub enum Subcommand {
 
 	/// Db meta columns information.
 	ChainInfo(sc_cli::ChainInfoCmd),
+
+	/// Export a portable, offline-verifiable proof-of-existence certificate for a claim.
+	ExportPoeCertificate(crate::certificate::ExportPoeCertificateCmd),
+
+	/// Decode a block or an extrinsic, with its events and storage changes, as JSON.
+	Inspect(crate::inspect::InspectCmd),
+
+	/// Sign and submit a proof-of-existence or file storage transaction to a node.
+	Tx(crate::tx::TxCmd),
 }
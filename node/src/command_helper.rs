@@ -70,10 +70,33 @@ pub fn create_benchmark_extrinsic(
 	call: runtime::Call,
 	nonce: u32,
 ) -> runtime::UncheckedExtrinsic {
-	let genesis_hash = client.block_hash(0).ok().flatten().expect("Genesis block exists; qed");
-	let best_hash = client.chain_info().best_hash;
-	let best_block = client.chain_info().best_number;
+	let context = SigningContext {
+		genesis_hash: client.block_hash(0).ok().flatten().expect("Genesis block exists; qed"),
+		best_hash: client.chain_info().best_hash,
+		best_number: client.chain_info().best_number,
+		spec_version: runtime::VERSION.spec_version,
+		transaction_version: runtime::VERSION.transaction_version,
+	};
+	create_signed_extrinsic(&sender, call, nonce, &context)
+}
+
+/// The chain a transaction is signed for, and the block its mortality period starts at.
+pub struct SigningContext {
+	pub genesis_hash: runtime::Hash,
+	pub best_hash: runtime::Hash,
+	pub best_number: runtime::BlockNumber,
+	pub spec_version: u32,
+	pub transaction_version: u32,
+}
 
+/// Create a transaction using the given `call`, signed by `sender` with the runtime's
+/// `SignedExtra` and no tip.
+pub fn create_signed_extrinsic(
+	sender: &sp_core::sr25519::Pair,
+	call: runtime::Call,
+	nonce: u32,
+	context: &SigningContext,
+) -> runtime::UncheckedExtrinsic {
 	let period = runtime::BlockHashCount::get()
 		.checked_next_power_of_two()
 		.map(|c| c / 2)
@@ -85,7 +108,7 @@ pub fn create_benchmark_extrinsic(
 		frame_system::CheckGenesis::<runtime::Runtime>::new(),
 		frame_system::CheckEra::<runtime::Runtime>::from(sp_runtime::generic::Era::mortal(
 			period,
-			best_block.saturated_into(),
+			context.best_number.saturated_into(),
 		)),
 		frame_system::CheckNonce::<runtime::Runtime>::from(nonce),
 		frame_system::CheckWeight::<runtime::Runtime>::new(),
@@ -97,10 +120,10 @@ pub fn create_benchmark_extrinsic(
 		extra.clone(),
 		(
 			(),
-			runtime::VERSION.spec_version,
-			runtime::VERSION.transaction_version,
-			genesis_hash,
-			best_hash,
+			context.spec_version,
+			context.transaction_version,
+			context.genesis_hash,
+			context.best_hash,
 			(),
 			(),
 			(),
@@ -109,10 +132,10 @@ pub fn create_benchmark_extrinsic(
 	let signature = raw_payload.using_encoded(|e| sender.sign(e));
 
 	runtime::UncheckedExtrinsic::new_signed(
-		call.clone(),
+		call,
 		sp_runtime::AccountId32::from(sender.public()).into(),
-		runtime::Signature::Sr25519(signature.clone()),
-		extra.clone(),
+		runtime::Signature::Sr25519(signature),
+		extra,
 	)
 }
 
@@ -0,0 +1,315 @@
+//! HTTP gateway serving the contents of registered files from the local [`BlobStore`].
+//!
+//! A file is downloaded with
+//!
+//! ```text
+//! GET /files/0x<cid>?account=<ss58 address>&expires=<unix seconds>&signature=0x<signature>
+//! ```
+//!
+//! where `signature` is the sr25519 signature of `account` over [`download_message`]. A request
+//! is only valid until `expires`, which may be at most [`MAX_VALIDITY`] ahead. The gateway asks
+//! the runtime, in the best block, whether `account` may download the file: the owner always
+//! may, anyone else only if the file allows downloads and is either not for sale or was
+//! purchased by them.
+//!
+//! Nothing is recorded on chain. Every served download is logged under the `file-gateway`
+//! target, for later settlement, and counted in the `file_gateway_downloads_total` metric.
+
+use crate::{blob_store::BlobStore, service::FullClient};
+use hyper::{
+	service::{make_service_fn, service_fn},
+	Body, Method, Request, Response, Server, StatusCode,
+};
+use node_template_runtime::{opaque::Block, AccountId, Hash};
+use pallet_file_storage_runtime_api::FileStorageApi;
+use sc_service::{error::Error as ServiceError, TaskManager};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{
+	crypto::{Pair as _, Ss58Codec},
+	sr25519,
+};
+use sp_runtime::generic::BlockId;
+use std::{
+	convert::Infallible,
+	net::SocketAddr,
+	sync::Arc,
+	time::{SystemTime, UNIX_EPOCH},
+};
+use substrate_prometheus_endpoint::{register, Counter, Registry, U64};
+use tokio_util::io::ReaderStream;
+
+/// How far in the future a request may expire, in seconds.
+pub const MAX_VALIDITY: u64 = 60 * 60;
+
+/// Prefix of the message signed for a download.
+const DOMAIN: &[u8] = b"file-gateway/download:";
+
+const LOG_TARGET: &str = "file-gateway";
+
+/// The message an account signs to download the file `cid` of the chain `genesis` until
+/// `expires`: `DOMAIN`, the 32 bytes of the genesis hash, the 32 bytes of `cid` and `expires` as
+/// 8 little-endian bytes.
+///
+/// The genesis hash keeps a signature for one chain from being replayed on another, such as a
+/// testnet and the chain it was forked from.
+pub fn download_message(genesis: &Hash, cid: &Hash, expires: u64) -> Vec<u8> {
+	[DOMAIN, genesis.as_bytes(), cid.as_bytes(), &expires.to_le_bytes()].concat()
+}
+
+/// Binds the gateway to `addr` and spawns it.
+pub fn spawn(
+	addr: SocketAddr,
+	client: Arc<FullClient>,
+	store: Arc<BlobStore>,
+	registry: Option<&Registry>,
+	task_manager: &TaskManager,
+) -> Result<(), ServiceError> {
+	let builder = Server::try_bind(&addr).map_err(|e| {
+		ServiceError::Other(format!("Failed to bind the file gateway to {}: {}", addr, e))
+	})?;
+	let downloads = registry
+		.map(|registry| {
+			let counter = Counter::new("file_gateway_downloads_total", "Files served.")?;
+			register(counter, registry)
+		})
+		.transpose()?;
+
+	let make_service = make_service_fn(move |_| {
+		let client = client.clone();
+		let store = store.clone();
+		let downloads = downloads.clone();
+		async move {
+			Ok::<_, Infallible>(service_fn(move |request| {
+				serve(request, client.clone(), store.clone(), downloads.clone())
+			}))
+		}
+	});
+	let server = builder.serve(make_service);
+
+	log::info!(target: LOG_TARGET, "Serving files on http://{}", addr);
+	task_manager.spawn_handle().spawn("file-gateway", None, async move {
+		if let Err(e) = server.await {
+			log::error!(target: LOG_TARGET, "File gateway failed: {}", e);
+		}
+	});
+	Ok(())
+}
+
+/// A download request that passed authentication.
+struct Download {
+	cid: Hash,
+	account: AccountId,
+}
+
+type Rejection = (StatusCode, &'static str);
+
+async fn serve(
+	request: Request<Body>,
+	client: Arc<FullClient>,
+	store: Arc<BlobStore>,
+	downloads: Option<Counter<U64>>,
+) -> Result<Response<Body>, Infallible> {
+	let response = match handle(request, &client, &store).await {
+		Ok(response) => {
+			if let Some(downloads) = downloads {
+				downloads.inc();
+			}
+			response
+		},
+		Err((status, message)) => {
+			let mut response = Response::new(Body::from(message));
+			*response.status_mut() = status;
+			response
+		},
+	};
+	Ok(response)
+}
+
+async fn handle(
+	request: Request<Body>,
+	client: &FullClient,
+	store: &BlobStore,
+) -> Result<Response<Body>, Rejection> {
+	if request.method() != Method::GET {
+		return Err((StatusCode::METHOD_NOT_ALLOWED, "Only GET is supported."))
+	}
+	let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+	let Download { cid, account } = authenticate(&request, &client.info().genesis_hash, now)?;
+
+	let at = BlockId::hash(client.info().best_hash);
+	let allowed = client.runtime_api().can_download(&at, cid, account.clone()).map_err(|e| {
+		log::warn!(target: LOG_TARGET, "Failed to check access to {:?}: {}", cid, e);
+		(StatusCode::INTERNAL_SERVER_ERROR, "Failed to check access.")
+	})?;
+	if !allowed {
+		return Err((StatusCode::FORBIDDEN, "The account may not download this file."))
+	}
+
+	let path = store
+		.file_path(&cid)
+		.ok_or((StatusCode::NOT_FOUND, "The file is not stored on this node."))?;
+	let file = tokio::fs::File::open(&path).await.map_err(|e| {
+		log::warn!(target: LOG_TARGET, "Failed to open {}: {}", path.display(), e);
+		(StatusCode::NOT_FOUND, "The file is not stored on this node.")
+	})?;
+	let size = file.metadata().await.map(|metadata| metadata.len()).ok();
+
+	log::info!(
+		target: LOG_TARGET,
+		"Serving {:?} to {} ({} bytes) at block {}",
+		cid,
+		account,
+		size.map_or_else(|| "?".into(), |size| size.to_string()),
+		client.info().best_number,
+	);
+
+	let mut response = Response::new(Body::wrap_stream(ReaderStream::new(file)));
+	let headers = response.headers_mut();
+	headers.insert(hyper::header::CONTENT_TYPE, "application/octet-stream".parse().unwrap());
+	if let Some(size) = size {
+		headers.insert(hyper::header::CONTENT_LENGTH, size.into());
+	}
+	Ok(response)
+}
+
+/// Checks the path, expiry and signature of `request` to the chain `genesis` at the unix time
+/// `now`.
+fn authenticate(request: &Request<Body>, genesis: &Hash, now: u64) -> Result<Download, Rejection> {
+	const BAD_REQUEST: Rejection = (
+		StatusCode::BAD_REQUEST,
+		"Expected GET /files/0x<cid>?account=<address>&expires=<seconds>&signature=0x<signature>.",
+	);
+
+	let cid = request
+		.uri()
+		.path()
+		.strip_prefix("/files/")
+		.and_then(|cid| parse_hex::<32>(cid))
+		.map(Hash::from)
+		.ok_or(BAD_REQUEST)?;
+
+	let (mut account, mut expires, mut signature) = (None, None, None);
+	for pair in request.uri().query().unwrap_or_default().split('&') {
+		match pair.split_once('=') {
+			Some(("account", value)) => account = AccountId::from_ss58check(value).ok(),
+			Some(("expires", value)) => expires = value.parse::<u64>().ok(),
+			Some(("signature", value)) => signature = parse_hex::<64>(value),
+			_ => {},
+		}
+	}
+	let (account, expires, signature) =
+		(account.ok_or(BAD_REQUEST)?, expires.ok_or(BAD_REQUEST)?, signature.ok_or(BAD_REQUEST)?);
+
+	if expires <= now {
+		return Err((StatusCode::UNAUTHORIZED, "The request has expired."))
+	}
+	if expires > now + MAX_VALIDITY {
+		return Err((StatusCode::UNAUTHORIZED, "The request expires too far in the future."))
+	}
+
+	let public = sr25519::Public(account.clone().into());
+	let signature = sr25519::Signature::from_raw(signature);
+	if !sr25519::Pair::verify(&signature, download_message(genesis, &cid, expires), &public) {
+		return Err((StatusCode::UNAUTHORIZED, "Invalid signature."))
+	}
+
+	Ok(Download { cid, account })
+}
+
+fn parse_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+	sp_core::bytes::from_hex(s).ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::Pair;
+	use sp_keyring::AccountKeyring::{Alice, Bob};
+
+	const NOW: u64 = 1_600_000_000;
+
+	fn genesis() -> Hash {
+		Hash::repeat_byte(0xaa)
+	}
+
+	fn cid() -> Hash {
+		Hash::repeat_byte(1)
+	}
+
+	/// A request of `account` for `cid()` until `expires`, signed by `signer` for `genesis`.
+	fn request(
+		account: sp_keyring::AccountKeyring,
+		signer: sp_keyring::AccountKeyring,
+		genesis: Hash,
+		expires: u64,
+	) -> Request<Body> {
+		let signature = signer.pair().sign(&download_message(&genesis, &cid(), expires));
+		let uri = format!(
+			"/files/{}?account={}&expires={}&signature={}",
+			sp_core::bytes::to_hex(cid().as_bytes(), false),
+			account.to_account_id().to_ss58check(),
+			expires,
+			sp_core::bytes::to_hex(&signature.0, false),
+		);
+		Request::get(uri).body(Body::empty()).unwrap()
+	}
+
+	fn rejection(result: Result<Download, Rejection>) -> &'static str {
+		match result {
+			Ok(_) => panic!("the request was accepted"),
+			Err((status, message)) => {
+				assert_eq!(status, StatusCode::UNAUTHORIZED);
+				message
+			},
+		}
+	}
+
+	#[test]
+	fn accepts_a_request_signed_by_the_account() {
+		let request = request(Alice, Alice, genesis(), NOW + 60);
+		let download = authenticate(&request, &genesis(), NOW).unwrap();
+		assert_eq!(download.cid, cid());
+		assert_eq!(download.account, Alice.to_account_id());
+	}
+
+	#[test]
+	fn accepts_the_longest_validity() {
+		let request = request(Alice, Alice, genesis(), NOW + MAX_VALIDITY);
+		assert!(authenticate(&request, &genesis(), NOW).is_ok());
+	}
+
+	#[test]
+	fn rejects_an_expired_request() {
+		let request = request(Alice, Alice, genesis(), NOW);
+		assert_eq!(rejection(authenticate(&request, &genesis(), NOW)), "The request has expired.");
+	}
+
+	#[test]
+	fn rejects_a_request_expiring_too_late() {
+		let request = request(Alice, Alice, genesis(), NOW + MAX_VALIDITY + 1);
+		assert_eq!(
+			rejection(authenticate(&request, &genesis(), NOW)),
+			"The request expires too far in the future."
+		);
+	}
+
+	#[test]
+	fn rejects_a_signature_of_another_account() {
+		let request = request(Alice, Bob, genesis(), NOW + 60);
+		assert_eq!(rejection(authenticate(&request, &genesis(), NOW)), "Invalid signature.");
+	}
+
+	#[test]
+	fn rejects_a_signature_for_another_chain() {
+		let request = request(Alice, Alice, Hash::repeat_byte(0xbb), NOW + 60);
+		assert_eq!(rejection(authenticate(&request, &genesis(), NOW)), "Invalid signature.");
+	}
+
+	#[test]
+	fn rejects_a_malformed_request() {
+		let request = Request::get("/files/0x01?expires=1").body(Body::empty()).unwrap();
+		let (status, _) = authenticate(&request, &genesis(), NOW).err().unwrap();
+		assert_eq!(status, StatusCode::BAD_REQUEST);
+	}
+}
@@ -0,0 +1,422 @@
+//! The `generate-spec` subcommand.
+//!
+//! Builds a chain spec from a declarative TOML or JSON file instead of the Alice/Bob presets of
+//! [`crate::chain_spec`]. Keys are given as SS58 addresses or 0x-prefixed hex public keys and
+//! amounts in the smallest unit of the native currency, as numbers or, above `u64::MAX`, as
+//! decimal strings. A `sudo` key may only be given to a node built with the `dev-sudo` feature.
+//! A TOML example:
+//!
+//! ```toml
+//! name = "My Network"
+//! id = "my_network"
+//! chainType = "Live"
+//! protocolId = "mynet"
+//! bootNodes = ["/dns/boot.example.com/tcp/30333/p2p/12D3KooW..."]
+//! council = ["5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"]
+//!
+//! [properties]
+//! tokenSymbol = "UNIT"
+//! tokenDecimals = 12
+//!
+//! [[authorities]]
+//! account = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+//! aura = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+//! grandpa = "5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu"
+//!
+//! [[balances]]
+//! account = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+//! amount = "1000000000000000000"
+//!
+//! [[claims]]
+//! claim = "0x0101010101010101010101010101010101010101010101010101010101010101"
+//! owner = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+//!
+//! [[files]]
+//! cid = "0x0202020202020202020202020202020202020202020202020202020202020202"
+//! owner = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+//! link = "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"
+//! fileType = "Pdf"
+//! allowDownload = true
+//! size = 1024
+//! price = 1000000000000
+//! ```
+
+use crate::chain_spec::{self, ChainSpec, InitialFile};
+use node_template_runtime::{pallet_file_storage::FileType, AccountId, Balance, Hash, WASM_BINARY};
+use sc_cli::Result;
+use sc_service::{config::MultiaddrWithPeerId, ChainType, Properties};
+use serde::Deserialize;
+use sp_consensus_aura::sr25519::AuthorityId as AuraId;
+use sp_core::{
+	crypto::{ByteArray, Ss58Codec},
+	ed25519, sr25519,
+};
+use sp_finality_grandpa::AuthorityId as GrandpaId;
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
+
+/// The `generate-spec` command.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct GenerateSpecCmd {
+	/// The network description, in TOML if the file name ends with `.toml` and JSON otherwise.
+	#[clap(value_name = "CONFIG")]
+	pub config: PathBuf,
+
+	/// Emit the spec in raw form, with the genesis storage instead of the genesis config.
+	#[clap(long)]
+	pub raw: bool,
+
+	/// Write the spec to this file instead of stdout.
+	#[clap(long, short, value_name = "PATH")]
+	pub output: Option<PathBuf>,
+}
+
+/// The network described by a config file.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct NetworkConfig {
+	name: String,
+	id: String,
+	#[serde(default)]
+	chain_type: ChainType,
+	protocol_id: Option<String>,
+	#[serde(default)]
+	boot_nodes: Vec<String>,
+	properties: Option<Properties>,
+	sudo: Option<String>,
+	#[serde(default)]
+	council: Vec<String>,
+	authorities: Vec<AuthorityConfig>,
+	#[serde(default)]
+	balances: Vec<BalanceConfig>,
+	#[serde(default)]
+	claims: Vec<ClaimConfig>,
+	#[serde(default)]
+	files: Vec<FileConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct AuthorityConfig {
+	/// The validator's account, also the owner of its session keys.
+	account: String,
+	/// The sr25519 Aura key.
+	aura: String,
+	/// The ed25519 GRANDPA key.
+	grandpa: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct BalanceConfig {
+	account: String,
+	amount: Amount,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ClaimConfig {
+	claim: String,
+	owner: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct FileConfig {
+	cid: String,
+	owner: String,
+	link: String,
+	file_type: FileType,
+	#[serde(default)]
+	allow_download: bool,
+	size: u32,
+	price: Option<Amount>,
+}
+
+/// An amount, as a number or as a decimal string for values TOML and JSON numbers cannot hold.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Amount {
+	Number(u64),
+	Text(String),
+}
+
+impl Amount {
+	fn parse(&self) -> std::result::Result<Balance, String> {
+		match self {
+			Amount::Number(n) => Ok(*n as Balance),
+			Amount::Text(s) => s.parse().map_err(|e| format!("Invalid amount {:?}: {}", s, e)),
+		}
+	}
+}
+
+/// Parse a public key given as an SS58 address or 0x-prefixed hex.
+fn parse_key<T: Ss58Codec + ByteArray>(s: &str) -> std::result::Result<T, String> {
+	if s.starts_with("0x") {
+		let bytes = sp_core::bytes::from_hex(s).map_err(|e| format!("Invalid key {}: {}", s, e))?;
+		T::from_slice(&bytes).map_err(|()| format!("Invalid key {}: expected {} bytes", s, T::LEN))
+	} else {
+		T::from_ss58check(s).map_err(|e| format!("Invalid key {}: {:?}", s, e))
+	}
+}
+
+fn parse_account(s: &str) -> std::result::Result<AccountId, String> {
+	parse_key::<sr25519::Public>(s).map(Into::into)
+}
+
+fn parse_hash(s: &str) -> std::result::Result<Hash, String> {
+	let bytes = sp_core::bytes::from_hex(s).map_err(|e| format!("Invalid hash {}: {}", s, e))?;
+	if bytes.len() != 32 {
+		return Err(format!("Invalid hash {}: expected 32 bytes", s))
+	}
+	Ok(Hash::from_slice(&bytes))
+}
+
+impl NetworkConfig {
+	fn read(path: &Path) -> std::result::Result<Self, String> {
+		let content =
+			fs::read_to_string(path).map_err(|e| format!("Reading {}: {}", path.display(), e))?;
+		let is_toml = path.extension().map_or(false, |ext| ext == "toml");
+		Self::parse(&content, is_toml).map_err(|e| format!("Parsing {}: {}", path.display(), e))
+	}
+
+	/// Parse `content` as TOML if `is_toml` and as JSON otherwise.
+	fn parse(content: &str, is_toml: bool) -> std::result::Result<Self, String> {
+		if is_toml {
+			toml::from_str(content).map_err(|e| e.to_string())
+		} else {
+			serde_json::from_str(content).map_err(|e| e.to_string())
+		}
+	}
+
+	/// Build the chain spec, checking every key, hash and amount up front.
+	fn into_chain_spec(self) -> std::result::Result<ChainSpec, String> {
+		let wasm_binary = WASM_BINARY.ok_or_else(|| "Wasm binary not available".to_string())?;
+		if self.authorities.is_empty() {
+			return Err("At least one authority is required".into())
+		}
+
+		let authorities = self
+			.authorities
+			.iter()
+			.map(|a| {
+				Ok((
+					parse_account(&a.account)?,
+					AuraId::from(parse_key::<sr25519::Public>(&a.aura)?),
+					GrandpaId::from(parse_key::<ed25519::Public>(&a.grandpa)?),
+				))
+			})
+			.collect::<std::result::Result<Vec<_>, String>>()?;
+		if self.sudo.is_some() && !cfg!(feature = "dev-sudo") {
+			return Err("`sudo` requires a node built with the `dev-sudo` feature".into())
+		}
+		let root_key = self.sudo.as_deref().map(parse_account).transpose()?;
+		let council = self
+			.council
+			.iter()
+			.map(|s| parse_account(s))
+			.collect::<std::result::Result<Vec<_>, String>>()?;
+		let balances = self
+			.balances
+			.iter()
+			.map(|b| Ok((parse_account(&b.account)?, b.amount.parse()?)))
+			.collect::<std::result::Result<Vec<_>, String>>()?;
+		let claims = self
+			.claims
+			.iter()
+			.map(|c| Ok((parse_hash(&c.claim)?, parse_account(&c.owner)?)))
+			.collect::<std::result::Result<Vec<_>, String>>()?;
+		let files = self
+			.files
+			.into_iter()
+			.map(|f| {
+				Ok(InitialFile {
+					cid: parse_hash(&f.cid)?,
+					owner: parse_account(&f.owner)?,
+					file_link: f.link.into_bytes(),
+					file_type: f.file_type,
+					allow_download: f.allow_download,
+					file_size: f.size,
+					price: f.price.as_ref().map(Amount::parse).transpose()?,
+				})
+			})
+			.collect::<std::result::Result<Vec<_>, String>>()?;
+		let boot_nodes = self
+			.boot_nodes
+			.iter()
+			.map(|s| {
+				s.parse::<MultiaddrWithPeerId>()
+					.map_err(|e| format!("Invalid boot node {}: {}", s, e))
+			})
+			.collect::<std::result::Result<Vec<_>, String>>()?;
+
+		Ok(ChainSpec::from_genesis(
+			&self.name,
+			&self.id,
+			self.chain_type,
+			move || {
+				chain_spec::genesis(
+					wasm_binary,
+					authorities.clone(),
+					root_key.clone(),
+					council.clone(),
+					balances.clone(),
+					claims.clone(),
+					files.clone(),
+				)
+			},
+			boot_nodes,
+			None,
+			self.protocol_id.as_deref(),
+			None,
+			Some(self.properties.unwrap_or_else(chain_spec::chain_properties)),
+			None,
+		))
+	}
+}
+
+impl GenerateSpecCmd {
+	/// Run the command.
+	pub fn run(&self) -> Result<()> {
+		let spec = NetworkConfig::read(&self.config)?.into_chain_spec()?;
+		let json = sc_service::chain_ops::build_spec(&spec, self.raw)?;
+		match &self.output {
+			Some(path) => fs::write(path, json)?,
+			None => println!("{}", json),
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use node_template_runtime::GenesisConfig;
+	use serde_json::{json, Value};
+	use sp_keyring::{AccountKeyring, Ed25519Keyring};
+
+	/// The part of a chain spec holding the genesis config.
+	#[derive(Deserialize)]
+	struct Spec {
+		genesis: SpecGenesis,
+	}
+
+	#[derive(Deserialize)]
+	struct SpecGenesis {
+		runtime: GenesisConfig,
+	}
+
+	fn ss58(keyring: AccountKeyring) -> String {
+		keyring.public().to_ss58check()
+	}
+
+	fn hex(key: &[u8]) -> String {
+		sp_core::bytes::to_hex(key, false)
+	}
+
+	/// A network with Alice as its only authority, given as SS58 addresses.
+	fn network() -> Value {
+		json!({
+			"name": "Test",
+			"id": "test",
+			"authorities": [{
+				"account": ss58(AccountKeyring::Alice),
+				"aura": ss58(AccountKeyring::Alice),
+				"grandpa": Ed25519Keyring::Alice.public().to_ss58check(),
+			}],
+		})
+	}
+
+	fn spec(config: &Value) -> std::result::Result<String, String> {
+		let config = NetworkConfig::parse(&config.to_string(), false)?;
+		let spec = config.into_chain_spec()?;
+		sc_service::chain_ops::build_spec(&spec, false)
+	}
+
+	fn genesis(config: &Value) -> GenesisConfig {
+		let spec = spec(config).unwrap();
+		serde_json::from_str::<Spec>(&spec).unwrap().genesis.runtime
+	}
+
+	#[test]
+	fn toml_and_json_describe_the_same_network() {
+		let mut config = network();
+		config["balances"] = json!([{ "account": ss58(AccountKeyring::Bob), "amount": 1000 }]);
+		let toml = format!(
+			r#"
+			name = "Test"
+			id = "test"
+
+			[[authorities]]
+			account = "{alice}"
+			aura = "{alice}"
+			grandpa = "{grandpa}"
+
+			[[balances]]
+			account = "{bob}"
+			amount = 1000
+			"#,
+			alice = ss58(AccountKeyring::Alice),
+			grandpa = Ed25519Keyring::Alice.public().to_ss58check(),
+			bob = ss58(AccountKeyring::Bob),
+		);
+
+		let from_toml = NetworkConfig::parse(&toml, true).unwrap().into_chain_spec().unwrap();
+		assert_eq!(sc_service::chain_ops::build_spec(&from_toml, false), spec(&config));
+		assert!(NetworkConfig::parse(&config.to_string(), true).is_err());
+	}
+
+	#[test]
+	fn amounts_may_be_numbers_or_strings() {
+		let mut config = network();
+		config["balances"] = json!([
+			{ "account": ss58(AccountKeyring::Alice), "amount": 1000 },
+			{ "account": ss58(AccountKeyring::Bob), "amount": Balance::MAX.to_string() },
+		]);
+		assert_eq!(
+			genesis(&config).balances.balances,
+			vec![
+				(AccountKeyring::Alice.to_account_id(), 1000),
+				(AccountKeyring::Bob.to_account_id(), Balance::MAX),
+			]
+		);
+
+		config["balances"][1]["amount"] = json!("1e18");
+		assert!(spec(&config).unwrap_err().starts_with("Invalid amount \"1e18\""));
+	}
+
+	#[test]
+	fn keys_may_be_ss58_or_hex() {
+		let mut config = network();
+		config["authorities"] = json!([{
+			"account": hex(AccountKeyring::Alice.public().as_slice()),
+			"aura": hex(AccountKeyring::Alice.public().as_slice()),
+			"grandpa": hex(Ed25519Keyring::Alice.public().as_slice()),
+		}]);
+		assert_eq!(spec(&config), spec(&network()));
+
+		config["authorities"][0]["grandpa"] = json!("0x0102");
+		assert!(spec(&config).unwrap_err().ends_with("expected 32 bytes"));
+	}
+
+	#[cfg(not(feature = "dev-sudo"))]
+	#[test]
+	fn sudo_requires_the_dev_sudo_feature() {
+		let mut config = network();
+		config["sudo"] = json!(ss58(AccountKeyring::Alice));
+		assert_eq!(
+			spec(&config).unwrap_err(),
+			"`sudo` requires a node built with the `dev-sudo` feature"
+		);
+	}
+
+	#[cfg(feature = "dev-sudo")]
+	#[test]
+	fn sudo_sets_the_root_key() {
+		let mut config = network();
+		config["sudo"] = json!(ss58(AccountKeyring::Alice));
+		assert_eq!(genesis(&config).sudo.key, Some(AccountKeyring::Alice.to_account_id()));
+	}
+}
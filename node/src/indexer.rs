@@ -0,0 +1,807 @@
+//! Embedded index of the `FileStorage`, `PoeModule` and `Balances` events, in SQLite.
+//!
+//! The indexer follows the best chain: the events of every new best block are written to the
+//! database, after rolling back the indexed blocks of a retracted fork. Finalized blocks are
+//! marked as such and are never rolled back. The index is queried through the `indexer_events`
+//! and `indexer_status` RPC methods.
+//!
+//! Events are read from the state of their block and decoded with the metadata of the runtime
+//! that executed it, so the blocks of earlier runtime versions are indexed as well. A block whose
+//! events cannot be read, e.g. because its state was pruned, is indexed as incomplete: it is
+//! reported by `indexer_status` and read again when the node restarts. An index covering the
+//! whole chain needs an archive node (`--state-pruning archive`).
+
+use crate::{
+	inspect::{split_variant, MetadataCache},
+	service::{read_storage_value, FullClient},
+};
+use futures::{stream, StreamExt};
+use node_template_runtime::{AccountId, BlockNumber, Hash};
+use rusqlite::{named_params, Connection, OptionalExtension, Transaction};
+use sc_client_api::BlockchainEvents;
+use sc_service::{error::Error as ServiceError, TaskManager};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Header as HeaderT};
+use std::{
+	path::Path,
+	sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+/// The largest page `indexer_events` returns.
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+const LOG_TARGET: &str = "indexer";
+
+/// Version of [`SCHEMA`], kept in the `user_version` of the database. Indexes of another version
+/// are rebuilt; before version 1 events were decoded with the node's own runtime types, which
+/// misread the blocks of earlier runtimes.
+const SCHEMA_VERSION: u32 = 1;
+
+const SCHEMA: &str = "
+	PRAGMA journal_mode = WAL;
+
+	CREATE TABLE IF NOT EXISTS blocks (
+		number INTEGER PRIMARY KEY,
+		hash BLOB NOT NULL,
+		timestamp INTEGER NOT NULL,
+		finalized INTEGER NOT NULL DEFAULT 0,
+		complete INTEGER NOT NULL DEFAULT 1
+	);
+
+	CREATE TABLE IF NOT EXISTS events (
+		block_number INTEGER NOT NULL,
+		event_index INTEGER NOT NULL,
+		extrinsic_index INTEGER,
+		pallet TEXT NOT NULL,
+		name TEXT NOT NULL,
+		account BLOB,
+		counterparty BLOB,
+		subject BLOB,
+		amount TEXT,
+		details TEXT NOT NULL,
+		PRIMARY KEY (block_number, event_index)
+	);
+
+	CREATE INDEX IF NOT EXISTS events_by_account ON events (account);
+	CREATE INDEX IF NOT EXISTS events_by_counterparty ON events (counterparty);
+	CREATE INDEX IF NOT EXISTS events_by_subject ON events (subject);
+";
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Filter of `indexer_events`. Every field that is set must match.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct EventFilter {
+	/// `FileStorage`, `PoeModule` or `Balances`.
+	pub pallet: Option<String>,
+	/// Name of the event, e.g. `FileCreated`.
+	pub name: Option<String>,
+	/// An account the event is about, e.g. either side of a transfer.
+	pub account: Option<AccountId>,
+	/// The file or claim the event is about.
+	pub subject: Option<Hash>,
+	/// The first block to include.
+	pub from_block: Option<BlockNumber>,
+	/// The last block to include.
+	pub to_block: Option<BlockNumber>,
+	/// The earliest block time to include, in milliseconds since the Unix epoch.
+	pub from_time: Option<u64>,
+	/// The latest block time to include, in milliseconds since the Unix epoch.
+	pub to_time: Option<u64>,
+	/// Leave out the events of blocks that are not finalized yet.
+	pub finalized_only: bool,
+}
+
+/// An indexed event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedEvent {
+	/// Number of the block the event was emitted in.
+	pub block_number: BlockNumber,
+	/// Hash of the block the event was emitted in.
+	pub block_hash: Hash,
+	/// Time of the block, in milliseconds since the Unix epoch.
+	pub timestamp: u64,
+	/// Whether the block is finalized.
+	pub finalized: bool,
+	/// Index of the event in the block.
+	pub event_index: u32,
+	/// Index of the extrinsic that emitted the event, if any.
+	pub extrinsic_index: Option<u32>,
+	/// Name of the pallet that emitted the event.
+	pub pallet: String,
+	/// Name of the event.
+	pub name: String,
+	/// The account the event is about, e.g. the sender of a transfer.
+	pub account: Option<AccountId>,
+	/// The other account the event is about, e.g. the recipient of a transfer.
+	pub counterparty: Option<AccountId>,
+	/// The file or claim the event is about.
+	pub subject: Option<Hash>,
+	/// The amount the event is about, as a decimal string.
+	pub amount: Option<String>,
+	/// All fields of the event, as JSON.
+	pub details: String,
+}
+
+/// Progress of the indexer.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexerStatus {
+	/// The last indexed block.
+	pub best: Option<BlockNumber>,
+	/// The last indexed block that is finalized.
+	pub finalized: Option<BlockNumber>,
+	/// Number of indexed blocks whose events could not be read.
+	pub incomplete: u32,
+	/// The first indexed block whose events could not be read.
+	pub first_incomplete: Option<BlockNumber>,
+}
+
+/// The events the indexer keeps, with the fields holding the account, counterparty, subject and
+/// amount they are about. Fields are found by name, as pallets and events may have other indices
+/// in other runtime versions.
+const INDEXED_EVENTS: &[(&str, &str, [Option<&str>; 4])] = &[
+	("FileStorage", "ClaimCreated", [Some("who"), None, Some("claim"), None]),
+	("FileStorage", "ClaimRevoked", [Some("who"), None, Some("claim"), None]),
+	("FileStorage", "FileCreated", [Some("who"), None, Some("cid"), None]),
+	("FileStorage", "FileRemoved", [Some("owner"), None, Some("cid"), None]),
+	("FileStorage", "FilePurchased", [Some("buyer"), Some("seller"), Some("cid"), Some("price")]),
+	("PoeModule", "ClaimCreated", [Some("who"), None, Some("claim"), None]),
+	("PoeModule", "ClaimRevoked", [Some("who"), None, Some("claim"), None]),
+	("PoeModule", "ClaimTransferred", [Some("from"), Some("to"), Some("claim"), None]),
+	("Balances", "Endowed", [Some("account"), None, None, Some("free_balance")]),
+	("Balances", "DustLost", [Some("account"), None, None, Some("amount")]),
+	("Balances", "Transfer", [Some("from"), Some("to"), None, Some("amount")]),
+	("Balances", "BalanceSet", [Some("who"), None, None, Some("free")]),
+	("Balances", "Reserved", [Some("who"), None, None, Some("amount")]),
+	("Balances", "Unreserved", [Some("who"), None, None, Some("amount")]),
+	("Balances", "ReserveRepatriated", [Some("from"), Some("to"), None, Some("amount")]),
+	("Balances", "Deposit", [Some("who"), None, None, Some("amount")]),
+	("Balances", "Withdraw", [Some("who"), None, None, Some("amount")]),
+	("Balances", "Slashed", [Some("who"), None, None, Some("amount")]),
+];
+
+/// An event reduced to the columns of the `events` table.
+struct Row {
+	pallet: &'static str,
+	name: &'static str,
+	account: Option<AccountId>,
+	counterparty: Option<AccountId>,
+	subject: Option<Hash>,
+	amount: Option<String>,
+	details: String,
+}
+
+/// The row of the event `pallet::name` with the decoded `fields`, if it is an event the indexer
+/// keeps.
+fn describe(pallet: &str, name: &str, fields: &Value) -> Option<Row> {
+	let &(pallet, name, [account, counterparty, subject, amount]) =
+		INDEXED_EVENTS.iter().find(|(p, n, _)| *p == pallet && *n == name)?;
+	let field = |name: Option<&str>| name.and_then(|name| fields.get(name));
+	let bytes = |name: Option<&str>| -> Option<[u8; 32]> {
+		let hex = field(name)?.as_str()?;
+		sp_core::bytes::from_hex(hex).ok()?.try_into().ok()
+	};
+	Some(Row {
+		pallet,
+		name,
+		account: bytes(account).map(AccountId::new),
+		counterparty: bytes(counterparty).map(AccountId::new),
+		subject: bytes(subject).map(Hash::from),
+		amount: field(amount).and_then(|amount| match amount {
+			Value::String(amount) => Some(amount.clone()),
+			Value::Number(amount) => Some(amount.to_string()),
+			_ => None,
+		}),
+		details: fields.to_string(),
+	})
+}
+
+/// Pallet and name of the event `pallet::name`, if it is an event the indexer keeps.
+pub(crate) fn indexed_event(pallet: &str, name: &str) -> Option<(&'static str, &'static str)> {
+	describe(pallet, name, &Value::Null).map(|row| (row.pallet, row.name))
+}
+
+/// The rows of the kept events among the decoded `System::Events` records, with the index of
+/// each event and of the extrinsic that emitted it.
+fn rows(records: &[Value]) -> Vec<(u32, Option<u32>, Row)> {
+	records
+		.iter()
+		.enumerate()
+		.filter_map(|(index, record)| {
+			let extrinsic = record["phase"].get("ApplyExtrinsic").and_then(Value::as_u64);
+			let (pallet, event) = split_variant(&record["event"]);
+			let (name, fields) = split_variant(&event);
+			let row = describe(&pallet, &name, &fields)?;
+			Some((index as u32, extrinsic.map(|extrinsic| extrinsic as u32), row))
+		})
+		.collect()
+}
+
+/// Inserts the rows of the block `number`.
+fn insert_events(
+	tx: &Transaction,
+	number: BlockNumber,
+	events: &[(u32, Option<u32>, Row)],
+) -> rusqlite::Result<()> {
+	let mut insert = tx.prepare(
+		"INSERT INTO events (block_number, event_index, extrinsic_index, pallet, name, account, \
+		 counterparty, subject, amount, details) \
+		 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+	)?;
+	for (index, extrinsic, row) in events {
+		insert.execute(rusqlite::params![
+			number,
+			index,
+			extrinsic,
+			row.pallet,
+			row.name,
+			row.account.as_ref().map(AsRef::<[u8]>::as_ref),
+			row.counterparty.as_ref().map(AsRef::<[u8]>::as_ref),
+			row.subject.as_ref().map(Hash::as_bytes),
+			row.amount,
+			row.details,
+		])?;
+	}
+	Ok(())
+}
+
+/// The event index in SQLite.
+pub struct Indexer {
+	conn: Mutex<Connection>,
+}
+
+impl Indexer {
+	/// Opens the database at `path`, creating it if needed.
+	pub fn open(path: &Path) -> rusqlite::Result<Self> {
+		let conn = Connection::open(path)?;
+		let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+		if version != SCHEMA_VERSION {
+			conn.execute_batch("DROP TABLE IF EXISTS events; DROP TABLE IF EXISTS blocks;")?;
+		}
+		conn.execute_batch(SCHEMA)?;
+		conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+		Ok(Self { conn: Mutex::new(conn) })
+	}
+
+	fn conn(&self) -> MutexGuard<'_, Connection> {
+		self.conn.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Hash of the indexed block `number`, if any.
+	fn hash_at(&self, number: BlockNumber) -> rusqlite::Result<Option<Hash>> {
+		let hash = self
+			.conn()
+			.query_row("SELECT hash FROM blocks WHERE number = ?1", [number], |row| {
+				row.get::<_, Vec<u8>>(0)
+			})
+			.optional()?;
+		Ok(hash.map(|hash| Hash::from_slice(&hash)))
+	}
+
+	/// Removes the blocks above `number`, or all blocks if `number` is `None`, with their events.
+	/// Returns the number of removed blocks.
+	fn roll_back(&self, number: Option<BlockNumber>) -> rusqlite::Result<usize> {
+		let above = number.map_or(-1, i64::from);
+		let mut conn = self.conn();
+		let tx = conn.transaction()?;
+		tx.execute("DELETE FROM events WHERE block_number > ?1", [above])?;
+		let removed = tx.execute("DELETE FROM blocks WHERE number > ?1", [above])?;
+		tx.commit()?;
+		Ok(removed)
+	}
+
+	/// Writes the block `number` with its timestamp and events, or as incomplete if they could
+	/// not be read.
+	fn insert_block(
+		&self,
+		number: BlockNumber,
+		hash: Hash,
+		block: Option<(u64, &[(u32, Option<u32>, Row)])>,
+	) -> rusqlite::Result<()> {
+		let mut conn = self.conn();
+		let tx = conn.transaction()?;
+		let (timestamp, events) = block.unwrap_or_default();
+		tx.execute(
+			"INSERT INTO blocks (number, hash, timestamp, complete) VALUES (?1, ?2, ?3, ?4)",
+			rusqlite::params![number, hash.as_bytes(), timestamp, block.is_some()],
+		)?;
+		insert_events(&tx, number, events)?;
+		tx.commit()
+	}
+
+	/// Adds the timestamp and events of the incomplete block `number`, if it is still `hash`.
+	fn complete_block(
+		&self,
+		number: BlockNumber,
+		hash: Hash,
+		timestamp: u64,
+		events: &[(u32, Option<u32>, Row)],
+	) -> rusqlite::Result<()> {
+		let mut conn = self.conn();
+		let tx = conn.transaction()?;
+		let completed = tx.execute(
+			"UPDATE blocks SET timestamp = ?3, complete = 1 \
+			 WHERE number = ?1 AND hash = ?2 AND NOT complete",
+			rusqlite::params![number, hash.as_bytes(), timestamp],
+		)?;
+		if completed > 0 {
+			insert_events(&tx, number, events)?;
+		}
+		tx.commit()
+	}
+
+	/// Number and hash of the incomplete blocks.
+	fn incomplete(&self) -> rusqlite::Result<Vec<(BlockNumber, Hash)>> {
+		let conn = self.conn();
+		let mut query =
+			conn.prepare("SELECT number, hash FROM blocks WHERE NOT complete ORDER BY number")?;
+		let rows = query
+			.query_map([], |row| Ok((row.get(0)?, Hash::from_slice(&row.get::<_, Vec<u8>>(1)?))))?;
+		rows.collect()
+	}
+
+	/// Marks the blocks up to `number` as finalized.
+	fn finalize(&self, number: BlockNumber) -> rusqlite::Result<()> {
+		let sql = "UPDATE blocks SET finalized = 1 WHERE number <= ?1 AND finalized = 0";
+		self.conn().execute(sql, [number]).map(drop)
+	}
+
+	/// The last indexed and the last finalized block, and the incomplete blocks.
+	pub fn status(&self) -> rusqlite::Result<IndexerStatus> {
+		self.conn().query_row(
+			"SELECT MAX(number), MAX(CASE WHEN finalized THEN number END), \
+			 COUNT(CASE WHEN NOT complete THEN 1 END), MIN(CASE WHEN NOT complete THEN number END) \
+			 FROM blocks",
+			[],
+			|row| {
+				Ok(IndexerStatus {
+					best: row.get(0)?,
+					finalized: row.get(1)?,
+					incomplete: row.get(2)?,
+					first_incomplete: row.get(3)?,
+				})
+			},
+		)
+	}
+
+	/// Up to `limit` events matching `filter`, in chain order, continuing after the event
+	/// `start_after` (block number and event index) if given.
+	pub fn events(
+		&self,
+		filter: &EventFilter,
+		start_after: Option<(BlockNumber, u32)>,
+		limit: u32,
+	) -> rusqlite::Result<Vec<IndexedEvent>> {
+		let conn = self.conn();
+		let mut query = conn.prepare_cached(
+			"SELECT e.block_number, b.hash, b.timestamp, b.finalized, e.event_index, \
+			 e.extrinsic_index, e.pallet, e.name, e.account, e.counterparty, e.subject, e.amount, \
+			 e.details \
+			 FROM events e JOIN blocks b ON b.number = e.block_number \
+			 WHERE (:pallet IS NULL OR e.pallet = :pallet) \
+			 AND (:name IS NULL OR e.name = :name) \
+			 AND (:account IS NULL OR e.account = :account OR e.counterparty = :account) \
+			 AND (:subject IS NULL OR e.subject = :subject) \
+			 AND (:from_block IS NULL OR e.block_number >= :from_block) \
+			 AND (:to_block IS NULL OR e.block_number <= :to_block) \
+			 AND (:from_time IS NULL OR b.timestamp >= :from_time) \
+			 AND (:to_time IS NULL OR b.timestamp <= :to_time) \
+			 AND (NOT :finalized_only OR b.finalized) \
+			 AND (:after_block IS NULL \
+			 OR (e.block_number, e.event_index) > (:after_block, :after_index)) \
+			 ORDER BY e.block_number, e.event_index \
+			 LIMIT :limit",
+		)?;
+
+		let rows = query.query_map(
+			named_params! {
+				":pallet": filter.pallet,
+				":name": filter.name,
+				":account": filter.account.as_ref().map(AsRef::<[u8]>::as_ref),
+				":subject": filter.subject.as_ref().map(Hash::as_bytes),
+				":from_block": filter.from_block,
+				":to_block": filter.to_block,
+				":from_time": filter.from_time,
+				":to_time": filter.to_time,
+				":finalized_only": filter.finalized_only,
+				":after_block": start_after.map(|(block, _)| block),
+				":after_index": start_after.map(|(_, index)| index),
+				":limit": limit,
+			},
+			|row| {
+				let account = |index| -> rusqlite::Result<Option<AccountId>> {
+					let account = row.get::<_, Option<Vec<u8>>>(index)?;
+					Ok(account.and_then(|account| account.try_into().ok()).map(AccountId::new))
+				};
+				let hash = |index| {
+					row.get::<_, Option<Vec<u8>>>(index)
+						.map(|hash| hash.map(|hash| Hash::from_slice(&hash)))
+				};
+				Ok(IndexedEvent {
+					block_number: row.get(0)?,
+					block_hash: hash(1)?.unwrap_or_default(),
+					timestamp: row.get(2)?,
+					finalized: row.get(3)?,
+					event_index: row.get(4)?,
+					extrinsic_index: row.get(5)?,
+					pallet: row.get(6)?,
+					name: row.get(7)?,
+					account: account(8)?,
+					counterparty: account(9)?,
+					subject: hash(10)?,
+					amount: row.get(11)?,
+					details: row.get(12)?,
+				})
+			},
+		)?;
+		rows.collect()
+	}
+}
+
+/// The chain the indexer follows.
+trait Chain {
+	/// Number and parent hash of the block `hash`.
+	fn header(&self, hash: Hash) -> Result<(BlockNumber, Hash), Error>;
+
+	/// Timestamp and decoded `System::Events` records of the block `hash`.
+	fn block(&mut self, hash: Hash) -> Result<(u64, Vec<Value>), Error>;
+}
+
+/// The chain of the client, whose events are decoded with the metadata of the runtime that
+/// executed their block.
+struct ClientChain {
+	client: Arc<FullClient>,
+	metadata: MetadataCache,
+}
+
+impl Chain for ClientChain {
+	fn header(&self, hash: Hash) -> Result<(BlockNumber, Hash), Error> {
+		let header = self
+			.client
+			.header(BlockId::hash(hash))?
+			.ok_or_else(|| format!("Unknown block {:?}", hash))?;
+		Ok((*header.number(), *header.parent_hash()))
+	}
+
+	fn block(&mut self, hash: Hash) -> Result<(u64, Vec<Value>), Error> {
+		let at = BlockId::hash(hash);
+		let timestamp = read_storage_value::<u64>(&self.client, &at, b"Timestamp", b"Now")?;
+		Ok((timestamp.unwrap_or_default(), self.metadata.events(&self.client, hash)?))
+	}
+}
+
+/// Writes the block `number` with its events to the index, or as incomplete if its events
+/// cannot be read.
+fn index_block(
+	chain: &mut impl Chain,
+	indexer: &Indexer,
+	number: BlockNumber,
+	hash: Hash,
+) -> Result<(), Error> {
+	match chain.block(hash) {
+		Ok((timestamp, records)) =>
+			indexer.insert_block(number, hash, Some((timestamp, &rows(&records)[..])))?,
+		Err(e) => {
+			log::warn!(target: LOG_TARGET, "Indexing block #{} as incomplete: {}", number, e);
+			indexer.insert_block(number, hash, None)?;
+		},
+	}
+	Ok(())
+}
+
+/// Reads the events of the incomplete blocks again.
+fn retry_incomplete(chain: &mut impl Chain, indexer: &Indexer) -> Result<(), Error> {
+	let incomplete = indexer.incomplete()?;
+	let mut completed = 0;
+	for (number, hash) in &incomplete {
+		if let Ok((timestamp, records)) = chain.block(*hash) {
+			indexer.complete_block(*number, *hash, timestamp, &rows(&records))?;
+			completed += 1;
+		}
+	}
+	if !incomplete.is_empty() {
+		let left = incomplete.len() - completed;
+		log::info!(target: LOG_TARGET, "Completed {} blocks, {} stay incomplete", completed, left);
+	}
+	Ok(())
+}
+
+/// Makes the indexed chain end at `target`: rolls back the indexed blocks that are not its
+/// ancestors, then indexes the missing ancestors and `target`.
+fn sync_to(chain: &mut impl Chain, indexer: &Indexer, target: Hash) -> Result<(), Error> {
+	let mut enacted = Vec::new();
+	let mut hash = target;
+	let common = loop {
+		let (number, parent) = chain.header(hash)?;
+		if indexer.hash_at(number)? == Some(hash) {
+			break Some(number)
+		}
+		enacted.push((number, hash));
+		if number == 0 {
+			break None
+		}
+		hash = parent;
+	};
+
+	let retracted = indexer.roll_back(common)?;
+	if retracted > 0 {
+		log::info!(target: LOG_TARGET, "Rolled back {} retracted blocks", retracted);
+	}
+	for (number, hash) in enacted.into_iter().rev() {
+		index_block(chain, indexer, number, hash)?;
+	}
+	Ok(())
+}
+
+/// Marks `hash` and its ancestors as finalized, indexing them first if needed.
+fn finalize(
+	chain: &mut impl Chain,
+	indexer: &Indexer,
+	number: BlockNumber,
+	hash: Hash,
+) -> Result<(), Error> {
+	if indexer.hash_at(number)? != Some(hash) {
+		sync_to(chain, indexer, hash)?;
+	}
+	indexer.finalize(number)?;
+	Ok(())
+}
+
+/// Indexes the best chain as blocks are imported and finalized.
+pub async fn follow(client: Arc<FullClient>, indexer: Arc<Indexer>) {
+	enum Update {
+		Best(Hash),
+		Finalized(BlockNumber, Hash),
+	}
+
+	// Subscribe before catching up, so no block is missed in between.
+	let best = client
+		.import_notification_stream()
+		.filter(|notification| futures::future::ready(notification.is_new_best))
+		.map(|notification| Update::Best(notification.hash));
+	let finalized = client
+		.finality_notification_stream()
+		.map(|notification| Update::Finalized(*notification.header.number(), notification.hash));
+	let mut updates = stream::select(best, finalized);
+
+	let info = client.info();
+	let mut chain = ClientChain { client, metadata: MetadataCache::default() };
+	let catch_up = retry_incomplete(&mut chain, &indexer)
+		.and_then(|()| sync_to(&mut chain, &indexer, info.best_hash))
+		.and_then(|()| finalize(&mut chain, &indexer, info.finalized_number, info.finalized_hash));
+	if let Err(e) = catch_up {
+		log::error!(target: LOG_TARGET, "Failed to catch up with the chain: {}", e);
+	}
+
+	while let Some(update) = updates.next().await {
+		let result = match update {
+			Update::Best(hash) => sync_to(&mut chain, &indexer, hash),
+			Update::Finalized(number, hash) => finalize(&mut chain, &indexer, number, hash),
+		};
+		if let Err(e) = result {
+			log::error!(target: LOG_TARGET, "Failed to index the chain: {}", e);
+		}
+	}
+}
+
+/// Opens the index at `path` and spawns the task following the chain.
+pub fn spawn(
+	path: &Path,
+	client: Arc<FullClient>,
+	task_manager: &TaskManager,
+) -> Result<Arc<Indexer>, ServiceError> {
+	let indexer = Indexer::open(path).map_err(|e| {
+		ServiceError::Other(format!("Failed to open the index at {}: {}", path.display(), e))
+	})?;
+	let indexer = Arc::new(indexer);
+
+	// Writing to SQLite blocks, so the task gets a thread of its own.
+	task_manager
+		.spawn_handle()
+		.spawn_blocking("indexer", None, follow(client, indexer.clone()));
+	Ok(indexer)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+	use std::collections::{HashMap, HashSet};
+
+	/// Blocks by hash, with their number, parent and events.
+	#[derive(Default)]
+	struct TestChain {
+		blocks: HashMap<Hash, (BlockNumber, Hash, Vec<Value>)>,
+		unreadable: HashSet<Hash>,
+	}
+
+	impl TestChain {
+		/// Adds a block on top of `parent` with `events` and returns its hash.
+		fn add(&mut self, parent: Option<Hash>, fork: u8, events: Vec<Value>) -> Hash {
+			let number = parent.map_or(0, |parent| self.blocks[&parent].0 + 1);
+			let mut hash = Hash::repeat_byte(fork);
+			hash.as_bytes_mut()[..4].copy_from_slice(&number.to_be_bytes());
+			self.blocks.insert(hash, (number, parent.unwrap_or_default(), events));
+			hash
+		}
+	}
+
+	impl Chain for TestChain {
+		fn header(&self, hash: Hash) -> Result<(BlockNumber, Hash), Error> {
+			let (number, parent, _) = self.blocks.get(&hash).ok_or("Unknown block")?;
+			Ok((*number, *parent))
+		}
+
+		fn block(&mut self, hash: Hash) -> Result<(u64, Vec<Value>), Error> {
+			if self.unreadable.contains(&hash) {
+				return Err("State pruned".into())
+			}
+			let (number, _, events) = &self.blocks[&hash];
+			Ok((u64::from(*number) * 6000, events.clone()))
+		}
+	}
+
+	fn account(byte: u8) -> AccountId {
+		AccountId::new([byte; 32])
+	}
+
+	fn transfer(from: u8, to: u8, amount: u128) -> Value {
+		json!({
+			"phase": { "ApplyExtrinsic": 1 },
+			"event": { "Balances": { "Transfer": {
+				"from": format!("{:?}", Hash::repeat_byte(from)),
+				"to": format!("{:?}", Hash::repeat_byte(to)),
+				"amount": amount.to_string(),
+			} } },
+			"topics": [],
+		})
+	}
+
+	fn new_indexer() -> Indexer {
+		Indexer::open(Path::new(":memory:")).unwrap()
+	}
+
+	fn all_events(indexer: &Indexer) -> Vec<IndexedEvent> {
+		indexer.events(&EventFilter::default(), None, MAX_PAGE_SIZE).unwrap()
+	}
+
+	#[test]
+	fn sync_to_indexes_the_missing_blocks() {
+		let mut chain = TestChain::default();
+		let genesis = chain.add(None, 0, vec![]);
+		let one = chain.add(Some(genesis), 0, vec![json!({ "phase": "Initialization" })]);
+		let two = chain.add(Some(one), 0, vec![transfer(1, 2, 100)]);
+		let indexer = new_indexer();
+
+		sync_to(&mut chain, &indexer, two).unwrap();
+
+		let status = indexer.status().unwrap();
+		assert_eq!((status.best, status.finalized, status.incomplete), (Some(2), None, 0));
+		assert_eq!(indexer.hash_at(1).unwrap(), Some(one));
+		let events = all_events(&indexer);
+		assert_eq!(events.len(), 1);
+		let event = &events[0];
+		assert_eq!((event.block_number, event.block_hash, event.timestamp), (2, two, 12000));
+		assert_eq!((event.event_index, event.extrinsic_index), (0, Some(1)));
+		assert_eq!((event.pallet.as_str(), event.name.as_str()), ("Balances", "Transfer"));
+		assert_eq!(event.account, Some(account(1)));
+		assert_eq!(event.counterparty, Some(account(2)));
+		assert_eq!(event.amount.as_deref(), Some("100"));
+
+		// Already indexed blocks are left alone.
+		sync_to(&mut chain, &indexer, two).unwrap();
+		assert_eq!(all_events(&indexer).len(), 1);
+	}
+
+	#[test]
+	fn sync_to_rolls_back_a_retracted_fork() {
+		let mut chain = TestChain::default();
+		let genesis = chain.add(None, 0, vec![]);
+		let one = chain.add(Some(genesis), 0, vec![]);
+		let two = chain.add(Some(one), 0, vec![transfer(1, 2, 100)]);
+		let three = chain.add(Some(two), 0, vec![transfer(2, 3, 50)]);
+		let fork = chain.add(Some(one), 1, vec![transfer(1, 3, 10)]);
+		let indexer = new_indexer();
+		sync_to(&mut chain, &indexer, three).unwrap();
+
+		sync_to(&mut chain, &indexer, fork).unwrap();
+
+		assert_eq!(indexer.status().unwrap().best, Some(2));
+		assert_eq!(indexer.hash_at(2).unwrap(), Some(fork));
+		assert_eq!(indexer.hash_at(3).unwrap(), None);
+		let events = all_events(&indexer);
+		assert_eq!(events.len(), 1);
+		assert_eq!((events[0].block_hash, events[0].amount.as_deref()), (fork, Some("10")));
+	}
+
+	#[test]
+	fn roll_back_removes_the_blocks_above() {
+		let mut chain = TestChain::default();
+		let genesis = chain.add(None, 0, vec![transfer(1, 2, 1)]);
+		let one = chain.add(Some(genesis), 0, vec![transfer(1, 2, 2)]);
+		let two = chain.add(Some(one), 0, vec![transfer(1, 2, 3)]);
+		let indexer = new_indexer();
+		sync_to(&mut chain, &indexer, two).unwrap();
+
+		assert_eq!(indexer.roll_back(Some(2)).unwrap(), 0);
+		assert_eq!(indexer.roll_back(Some(0)).unwrap(), 2);
+		assert_eq!(indexer.status().unwrap().best, Some(0));
+		assert_eq!(all_events(&indexer).len(), 1);
+		assert_eq!(indexer.roll_back(None).unwrap(), 1);
+		assert_eq!(indexer.status().unwrap().best, None);
+		assert!(all_events(&indexer).is_empty());
+	}
+
+	#[test]
+	fn finalize_marks_the_ancestors() {
+		let mut chain = TestChain::default();
+		let genesis = chain.add(None, 0, vec![]);
+		let one = chain.add(Some(genesis), 0, vec![transfer(1, 2, 1)]);
+		let two = chain.add(Some(one), 0, vec![transfer(1, 2, 2)]);
+		let indexer = new_indexer();
+
+		// Finalizing a block that is not indexed yet indexes it.
+		finalize(&mut chain, &indexer, 1, one).unwrap();
+		sync_to(&mut chain, &indexer, two).unwrap();
+
+		assert_eq!(indexer.status().unwrap().finalized, Some(1));
+		let filter = EventFilter { finalized_only: true, ..Default::default() };
+		let events = indexer.events(&filter, None, MAX_PAGE_SIZE).unwrap();
+		assert_eq!(events.len(), 1);
+		assert!(events[0].finalized);
+		assert!(!all_events(&indexer)[1].finalized);
+	}
+
+	#[test]
+	fn unreadable_blocks_are_reported_and_retried() {
+		let mut chain = TestChain::default();
+		let genesis = chain.add(None, 0, vec![]);
+		let one = chain.add(Some(genesis), 0, vec![transfer(1, 2, 1)]);
+		let two = chain.add(Some(one), 0, vec![transfer(1, 2, 2)]);
+		chain.unreadable.insert(one);
+		let indexer = new_indexer();
+
+		sync_to(&mut chain, &indexer, two).unwrap();
+
+		let status = indexer.status().unwrap();
+		assert_eq!(status.best, Some(2));
+		assert_eq!((status.incomplete, status.first_incomplete), (1, Some(1)));
+		assert_eq!(all_events(&indexer).len(), 1);
+
+		// Blocks that still cannot be read stay incomplete.
+		retry_incomplete(&mut chain, &indexer).unwrap();
+		assert_eq!(indexer.status().unwrap().incomplete, 1);
+
+		chain.unreadable.clear();
+		retry_incomplete(&mut chain, &indexer).unwrap();
+		let status = indexer.status().unwrap();
+		assert_eq!((status.incomplete, status.first_incomplete), (0, None));
+		let events = all_events(&indexer);
+		assert_eq!(events.len(), 2);
+		assert_eq!((events[0].block_number, events[0].timestamp), (1, 6000));
+	}
+
+	#[test]
+	fn describe_reads_fields_by_name() {
+		let fields = json!({
+			"buyer": format!("{:?}", Hash::repeat_byte(1)),
+			"seller": format!("{:?}", Hash::repeat_byte(2)),
+			"cid": format!("{:?}", Hash::repeat_byte(3)),
+			"asset_id": null,
+			"price": "1000000000000000000000",
+		});
+		let row = describe("FileStorage", "FilePurchased", &fields).unwrap();
+		assert_eq!((row.account, row.counterparty), (Some(account(1)), Some(account(2))));
+		assert_eq!(row.subject, Some(Hash::repeat_byte(3)));
+		assert_eq!(row.amount.as_deref(), Some("1000000000000000000000"));
+		assert_eq!(row.details, fields.to_string());
+
+		assert!(describe("FileStorage", "Unknown", &fields).is_none());
+		assert!(describe("System", "ExtrinsicSuccess", &Value::Null).is_none());
+	}
+}
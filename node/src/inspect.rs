@@ -0,0 +1,596 @@
+//! The `inspect` subcommand.
+//!
+//! Prints a block or an extrinsic as JSON. Calls, signed extras, events and storage are decoded
+//! with the metadata of the runtime that executed the block, read from the local database, so
+//! blocks of earlier runtime versions decode as well. That runtime is the one of the parent's
+//! state, which differs from the block's own if the block upgraded the runtime; only the genesis
+//! block is decoded with its own. For a block the output holds each extrinsic with its signer,
+//! signed extras, call and events, the events emitted outside of extrinsics and the storage
+//! changes the block made to the pallets of this repository. Storage changes are found by
+//! comparing the block's state to its parent's, so both states must not have been pruned, and
+//! the parent's values are decoded with the metadata of the runtime that executed the parent. A
+//! hex extrinsic is decoded with the metadata of the best block, and has no events or storage
+//! changes as it was not executed.
+
+use crate::service::{storage_prefix, FullClient};
+use codec::{Compact, Decode, Encode};
+use frame_metadata::{
+	RuntimeMetadata, RuntimeMetadataPrefixed, RuntimeMetadataV14, StorageEntryType, StorageHasher,
+};
+use node_template_runtime::{AccountId, BlockNumber, Hash, Header};
+use sc_cli::{CliConfiguration, DatabaseParams, PruningParams, Result, SharedParams};
+use sc_client_api::{BlockBackend, StorageProvider};
+use scale_info::{form::PortableForm, Field, PortableRegistry, TypeDef, TypeDefPrimitive};
+use serde_json::{json, Map, Value};
+use sp_api::{Core, Metadata, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_core::{
+	crypto::Ss58Codec,
+	hashing::{blake2_256, twox_128},
+	storage::{well_known_keys, StorageKey},
+};
+use sp_runtime::{generic::BlockId, traits::Header as HeaderT};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	sync::Arc,
+};
+
+/// Pallets of this repository, whose storage changes are decoded.
+const CUSTOM_PALLETS: [&str; 6] =
+	["ValidatorSet", "RandomnessBeacon", "TxPause", "Parameters", "PoeModule", "FileStorage"];
+
+/// What to inspect.
+#[derive(Debug, Clone)]
+pub enum Target {
+	Number(BlockNumber),
+	Hash(Hash),
+	Extrinsic(Vec<u8>),
+}
+
+fn parse_target(s: &str) -> std::result::Result<Target, String> {
+	if !s.starts_with("0x") {
+		return s.parse().map(Target::Number).map_err(|e| format!("Invalid block: {}", e))
+	}
+	let bytes = sp_core::bytes::from_hex(s).map_err(|e| format!("{:?}", e))?;
+	Ok(match bytes.len() {
+		32 => Target::Hash(Hash::from_slice(&bytes)),
+		_ => Target::Extrinsic(bytes),
+	})
+}
+
+/// The `inspect` command.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct InspectCmd {
+	/// A block number, a 0x-prefixed block hash or a 0x-prefixed SCALE-encoded extrinsic.
+	#[clap(value_name = "TARGET", parse(try_from_str = parse_target))]
+	pub target: Target,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl InspectCmd {
+	/// Run the command against the given client.
+	pub fn run(&self, client: Arc<FullClient>) -> Result<()> {
+		let output = match &self.target {
+			Target::Extrinsic(bytes) => {
+				let metadata = metadata_at(&client, client.info().best_hash)?;
+				decode_extrinsic(&metadata, bytes)?
+			},
+			Target::Hash(hash) => inspect_block(&client, *hash)?,
+			Target::Number(number) => {
+				let hash = client.hash(*number)?.ok_or_else(|| format!("No block {}.", number))?;
+				inspect_block(&client, hash)?
+			},
+		};
+		let json = serde_json::to_string_pretty(&output)
+			.map_err(|e| format!("Serializing the output: {}", e))?;
+		println!("{}", json);
+		Ok(())
+	}
+}
+
+impl CliConfiguration for InspectCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
+
+/// The metadata of the runtime of block `hash`.
+fn metadata_at(client: &FullClient, hash: Hash) -> Result<RuntimeMetadataV14> {
+	let opaque = client
+		.runtime_api()
+		.metadata(&BlockId::Hash(hash))
+		.map_err(|e| format!("Reading the metadata of {:?}: {}", hash, e))?;
+	let prefixed = RuntimeMetadataPrefixed::decode(&mut &opaque[..])
+		.map_err(|e| format!("Decoding the metadata of {:?}: {}", hash, e))?;
+	match prefixed.1 {
+		RuntimeMetadata::V14(metadata) => Ok(metadata),
+		_ => Err(format!("Block {:?} has metadata of an unsupported version.", hash).into()),
+	}
+}
+
+/// The block whose state holds the runtime that executed the block with `header`.
+///
+/// That is its parent, except for the genesis block, which was not executed.
+fn executed_at(header: &Header) -> Hash {
+	match *header.number() {
+		0 => header.hash(),
+		_ => *header.parent_hash(),
+	}
+}
+
+/// The metadata of the runtime that executed the last block asked for, as consecutive blocks
+/// are mostly executed by the same runtime and reading the metadata calls into it.
+#[derive(Default)]
+pub(crate) struct MetadataCache {
+	/// Hash of the runtime code, with its metadata.
+	last: Option<(Hash, Arc<RuntimeMetadataV14>)>,
+}
+
+impl MetadataCache {
+	/// The metadata of the runtime that executed the block with `header`.
+	fn executed(
+		&mut self,
+		client: &FullClient,
+		header: &Header,
+	) -> Result<Arc<RuntimeMetadataV14>> {
+		let at = executed_at(header);
+		let code = client
+			.storage_hash(&BlockId::Hash(at), &StorageKey(well_known_keys::CODE.to_vec()))?
+			.ok_or_else(|| format!("Block {:?} has no runtime code.", at))?;
+		match &self.last {
+			Some((cached, metadata)) if *cached == code => Ok(metadata.clone()),
+			_ => {
+				let metadata = Arc::new(metadata_at(client, at)?);
+				self.last = Some((code, metadata.clone()));
+				Ok(metadata)
+			},
+		}
+	}
+
+	/// The `System::Events` records of block `hash`, decoded with the metadata of the runtime
+	/// that executed it.
+	pub(crate) fn events(&mut self, client: &FullClient, hash: Hash) -> Result<Vec<Value>> {
+		let header = client
+			.header(BlockId::Hash(hash))?
+			.ok_or_else(|| format!("No block {:?}.", hash))?;
+		let metadata = self.executed(client, &header)?;
+		read_events(client, &metadata, hash)
+	}
+}
+
+fn inspect_block(client: &FullClient, hash: Hash) -> Result<Value> {
+	let block = client
+		.block(&BlockId::Hash(hash))?
+		.ok_or_else(|| format!("No block {:?}.", hash))?
+		.block;
+	let header = &block.header;
+	let runtime = executed_at(header);
+	let metadata = metadata_at(client, runtime)?;
+	let version = client
+		.runtime_api()
+		.version(&BlockId::Hash(runtime))
+		.map_err(|e| format!("Reading the runtime version of {:?}: {}", runtime, e))?;
+
+	let mut extrinsics = block
+		.extrinsics
+		.iter()
+		.map(|xt| decode_extrinsic(&metadata, &xt.encode()))
+		.collect::<std::result::Result<Vec<_>, _>>()?;
+	let mut block_events = Vec::new();
+	for record in read_events(client, &metadata, hash)? {
+		let phase = &record["phase"];
+		let (pallet, event) = split_variant(&record["event"]);
+		let (name, fields) = split_variant(&event);
+		let event = json!({ "pallet": pallet, "event": name, "fields": fields });
+		match phase.get("ApplyExtrinsic").and_then(Value::as_u64) {
+			Some(index) if (index as usize) < extrinsics.len() => {
+				let events = extrinsics[index as usize]["events"].as_array_mut();
+				events.expect("Decoded extrinsics have an events array; qed").push(event)
+			},
+			_ => block_events.push(json!({ "phase": phase, "event": event })),
+		}
+	}
+
+	let parent = match *header.number() {
+		0 => None,
+		_ => {
+			let parent = *header.parent_hash();
+			let parent_header = client
+				.header(BlockId::Hash(parent))?
+				.ok_or_else(|| format!("No block {:?}.", parent))?;
+			Some((parent, metadata_at(client, executed_at(&parent_header))?))
+		},
+	};
+	Ok(json!({
+		"number": header.number(),
+		"hash": hash,
+		"parentHash": header.parent_hash(),
+		"specVersion": version.spec_version,
+		"extrinsics": extrinsics,
+		"events": block_events,
+		"storageChanges": storage_changes(client, &metadata, parent.as_ref(), hash)?,
+	}))
+}
+
+/// The decoded `System::Events` records of block `hash`.
+fn read_events(
+	client: &FullClient,
+	metadata: &RuntimeMetadataV14,
+	hash: Hash,
+) -> Result<Vec<Value>> {
+	let ty = value_type(metadata, "System", "Events")
+		.ok_or("The metadata has no System::Events storage.")?;
+	let key = storage_prefix(b"System", b"Events");
+	let events = match client.storage(&BlockId::Hash(hash), &key)? {
+		Some(events) => decode_value(&metadata.types, ty, &mut &events.0[..])?,
+		None => return Ok(Vec::new()),
+	};
+	match events {
+		Value::Array(records) => Ok(records),
+		_ => Err("System::Events is not a sequence.".into()),
+	}
+}
+
+/// Changes made by block `hash` to the storage of [`CUSTOM_PALLETS`], compared to `parent`.
+///
+/// New values are decoded with `metadata`, old ones with the metadata given with `parent`.
+fn storage_changes(
+	client: &FullClient,
+	metadata: &RuntimeMetadataV14,
+	parent: Option<&(Hash, RuntimeMetadataV14)>,
+	hash: Hash,
+) -> Result<Vec<Value>> {
+	let pairs = |hash: Hash, prefix: &StorageKey| -> Result<BTreeMap<Vec<u8>, Vec<u8>>> {
+		let pairs = client.storage_pairs(&BlockId::Hash(hash), prefix).map_err(|e| {
+			format!("Reading the state of {:?}, which may have been pruned: {}", hash, e)
+		})?;
+		Ok(pairs.into_iter().map(|(key, value)| (key.0, value.0)).collect())
+	};
+
+	let mut changes = Vec::new();
+	for pallet in &metadata.pallets {
+		let storage = match &pallet.storage {
+			Some(storage) if CUSTOM_PALLETS.contains(&pallet.name.as_str()) => storage,
+			_ => continue,
+		};
+		let prefix = StorageKey(twox_128(storage.prefix.as_bytes()).to_vec());
+		let before = match parent {
+			Some((parent, _)) => pairs(*parent, &prefix)?,
+			None => BTreeMap::new(),
+		};
+		let after = pairs(hash, &prefix)?;
+
+		let keys: BTreeSet<&Vec<u8>> = before.keys().chain(after.keys()).collect();
+		for key in keys {
+			let (old, new) = (before.get(key), after.get(key));
+			if old == new {
+				continue
+			}
+			let entry = storage
+				.entries
+				.iter()
+				.find(|entry| key.get(16..32) == Some(&twox_128(entry.name.as_bytes())[..]));
+			let entry = match entry {
+				Some(entry) => entry,
+				None => {
+					changes.push(json!({
+						"pallet": pallet.name,
+						"key": hex(key),
+						"old": old.map(|v| hex(v)),
+						"new": new.map(|v| hex(v)),
+					}));
+					continue
+				},
+			};
+			let keys = match &entry.ty {
+				StorageEntryType::Plain(_) => Value::Null,
+				StorageEntryType::Map { hashers, key: key_ty, .. } =>
+					decode_map_keys(&metadata.types, hashers, key_ty.id(), &key[32..]),
+			};
+			// The item may have had another type, or not have existed, before the block.
+			let decode = |metadata: &RuntimeMetadataV14, value: &Vec<u8>| {
+				value_type(metadata, &pallet.name, &entry.name)
+					.and_then(|ty| decode_value(&metadata.types, ty, &mut &value[..]).ok())
+					.unwrap_or_else(|| Value::String(hex(value)))
+			};
+			let old_metadata = parent.map_or(metadata, |(_, metadata)| metadata);
+			changes.push(json!({
+				"pallet": pallet.name,
+				"item": entry.name,
+				"keys": keys,
+				"key": hex(key),
+				"old": old.map(|value| decode(old_metadata, value)),
+				"new": new.map(|value| decode(metadata, value)),
+			}));
+		}
+	}
+	Ok(changes)
+}
+
+/// The type of the values of the storage item `item` of `pallet`, if `metadata` has it.
+fn value_type(metadata: &RuntimeMetadataV14, pallet: &str, item: &str) -> Option<u32> {
+	let storage = metadata.pallets.iter().find(|p| p.name == pallet)?.storage.as_ref()?;
+	storage.entries.iter().find(|entry| entry.name == item).map(|entry| match &entry.ty {
+		StorageEntryType::Plain(ty) => ty.id(),
+		StorageEntryType::Map { value, .. } => value.id(),
+	})
+}
+
+/// The keys of a map entry, or `null` for keys hidden behind a non-concatenating hasher.
+fn decode_map_keys(
+	types: &PortableRegistry,
+	hashers: &[StorageHasher],
+	key_ty: u32,
+	mut input: &[u8],
+) -> Value {
+	// Maps with several hashers take a tuple of their keys.
+	let key_types = match types.resolve(key_ty).map(|ty| ty.type_def()) {
+		Some(TypeDef::Tuple(tuple)) if hashers.len() > 1 =>
+			tuple.fields().iter().map(|ty| ty.id()).collect(),
+		_ => vec![key_ty],
+	};
+	let mut keys = Vec::new();
+	for (hasher, ty) in hashers.iter().zip(key_types) {
+		let (hash_len, concat) = match hasher {
+			StorageHasher::Blake2_128 | StorageHasher::Twox128 => (16, false),
+			StorageHasher::Blake2_256 | StorageHasher::Twox256 => (32, false),
+			StorageHasher::Blake2_128Concat => (16, true),
+			StorageHasher::Twox64Concat => (8, true),
+			StorageHasher::Identity => (0, true),
+		};
+		if input.len() < hash_len {
+			return Value::Null
+		}
+		input = &input[hash_len..];
+		if !concat {
+			keys.push(Value::Null);
+			continue
+		}
+		match decode_value(types, ty, &mut input) {
+			Ok(key) => keys.push(key),
+			Err(_) => return Value::Null,
+		}
+	}
+	Value::Array(keys)
+}
+
+/// Decode an extrinsic of format version 4 into its signer, signed extras and call.
+fn decode_extrinsic(metadata: &RuntimeMetadataV14, bytes: &[u8]) -> Result<Value> {
+	let types = &metadata.types;
+	let param = |name: &str| {
+		types
+			.resolve(metadata.extrinsic.ty.id())
+			.and_then(|ty| ty.type_params().iter().find(|param| param.name() == name))
+			.and_then(|param| param.ty())
+			.map(|ty| ty.id())
+			.ok_or_else(|| format!("The metadata has no extrinsic {} type.", name))
+	};
+	let input = &mut &bytes[..];
+	Compact::<u32>::decode(input).map_err(codec_error)?;
+	let version = u8::decode(input).map_err(codec_error)?;
+	if version & 0b0111_1111 != metadata.extrinsic.version {
+		return Err(format!("Unsupported extrinsic version {}.", version & 0b0111_1111).into())
+	}
+
+	let (signer, extras) = if version & 0b1000_0000 != 0 {
+		let address = decode_value(types, param("Address")?, input)?;
+		decode_value(types, param("Signature")?, input)?;
+		let mut extras = Map::new();
+		for extension in &metadata.extrinsic.signed_extensions {
+			let extra = decode_value(types, extension.ty.id(), input)?;
+			extras.insert(extension.identifier.clone(), extra);
+		}
+		(signer(address), Value::Object(extras))
+	} else {
+		(Value::Null, Value::Null)
+	};
+	let call = decode_value(types, param("Call")?, input)?;
+	if !input.is_empty() {
+		return Err("The extrinsic has trailing bytes.".into())
+	}
+
+	let (pallet, call) = split_variant(&call);
+	let (name, args) = split_variant(&call);
+	Ok(json!({
+		"hash": Hash(blake2_256(bytes)),
+		"signer": signer,
+		"extras": extras,
+		"pallet": pallet,
+		"call": name,
+		"args": args,
+		"events": [],
+	}))
+}
+
+/// The SS58 address of a `MultiAddress::Id`, or the decoded address itself.
+fn signer(address: Value) -> Value {
+	let account = address
+		.get("Id")
+		.and_then(Value::as_str)
+		.and_then(|id| sp_core::bytes::from_hex(id).ok())
+		.and_then(|id| <[u8; 32]>::try_from(id).ok());
+	match account {
+		Some(account) => Value::String(AccountId::from(account).to_ss58check()),
+		None => address,
+	}
+}
+
+/// Split a decoded enum value into its variant name and its fields.
+pub(crate) fn split_variant(value: &Value) -> (String, Value) {
+	match value {
+		Value::String(name) => (name.clone(), Value::Null),
+		Value::Object(object) if object.len() == 1 => {
+			let (name, fields) = object.iter().next().expect("The object has one entry; qed");
+			(name.clone(), fields.clone())
+		},
+		_ => (String::new(), value.clone()),
+	}
+}
+
+fn hex(bytes: &[u8]) -> String {
+	format!("0x{}", sp_core::hexdisplay::HexDisplay::from(&bytes))
+}
+
+fn codec_error(e: codec::Error) -> String {
+	format!("Decoding failed: {}", e)
+}
+
+/// Decode a value of type `id` into JSON.
+///
+/// Structs become objects, or their only field if it is unnamed, and tuples become arrays. Enum
+/// variants become their name if they have no fields, and an object mapping their name to their
+/// fields otherwise. Byte strings become hex, and integers that JSON cannot hold exactly become
+/// decimal strings.
+fn decode_value(
+	types: &PortableRegistry,
+	id: u32,
+	input: &mut &[u8],
+) -> std::result::Result<Value, String> {
+	let ty = types.resolve(id).ok_or_else(|| format!("Unknown type {}.", id))?;
+	match ty.type_def() {
+		TypeDef::Composite(composite) => decode_fields(types, composite.fields(), input),
+		TypeDef::Variant(variant) => {
+			let index = u8::decode(input).map_err(codec_error)?;
+			let variant = variant
+				.variants()
+				.iter()
+				.find(|variant| variant.index() == index)
+				.ok_or_else(|| format!("Unknown variant {} of type {}.", index, id))?;
+			if variant.fields().is_empty() {
+				return Ok(Value::String(variant.name().clone()))
+			}
+			let mut object = Map::new();
+			object.insert(variant.name().clone(), decode_fields(types, variant.fields(), input)?);
+			Ok(Value::Object(object))
+		},
+		TypeDef::Sequence(sequence) => {
+			let len = Compact::<u32>::decode(input).map_err(codec_error)?.0;
+			decode_items(types, sequence.type_param().id(), len as usize, input)
+		},
+		TypeDef::Array(array) =>
+			decode_items(types, array.type_param().id(), array.len() as usize, input),
+		TypeDef::Tuple(tuple) if tuple.fields().is_empty() => Ok(Value::Null),
+		TypeDef::Tuple(tuple) => tuple
+			.fields()
+			.iter()
+			.map(|field| decode_value(types, field.id(), input))
+			.collect::<std::result::Result<_, _>>()
+			.map(Value::Array),
+		TypeDef::Primitive(primitive) => decode_primitive(primitive, input),
+		TypeDef::Compact(compact) => {
+			let value = Compact::<u128>::decode(input).map_err(codec_error)?.0;
+			Ok(match u64::try_from(value) {
+				Ok(value) if !is_u128(types, compact.type_param().id()) => value.into(),
+				_ => value.to_string().into(),
+			})
+		},
+		TypeDef::BitSequence(_) => Err("Bit sequences are not supported.".into()),
+	}
+}
+
+fn decode_fields(
+	types: &PortableRegistry,
+	fields: &[Field<PortableForm>],
+	input: &mut &[u8],
+) -> std::result::Result<Value, String> {
+	match fields {
+		[] => Ok(Value::Null),
+		[field] if field.name().is_none() => decode_value(types, field.ty().id(), input),
+		_ if fields.iter().all(|field| field.name().is_some()) => {
+			let mut object = Map::new();
+			for field in fields {
+				let name = field.name().expect("All fields are named; qed").clone();
+				object.insert(name, decode_value(types, field.ty().id(), input)?);
+			}
+			Ok(Value::Object(object))
+		},
+		_ => fields
+			.iter()
+			.map(|field| decode_value(types, field.ty().id(), input))
+			.collect::<std::result::Result<_, _>>()
+			.map(Value::Array),
+	}
+}
+
+fn decode_items(
+	types: &PortableRegistry,
+	item: u32,
+	len: usize,
+	input: &mut &[u8],
+) -> std::result::Result<Value, String> {
+	let is_byte = matches!(
+		types.resolve(item).map(|ty| ty.type_def()),
+		Some(TypeDef::Primitive(TypeDefPrimitive::U8))
+	);
+	if is_byte {
+		if input.len() < len {
+			return Err("Not enough data to fill the byte string.".into())
+		}
+		let (bytes, rest) = input.split_at(len);
+		*input = rest;
+		return Ok(Value::String(hex(bytes)))
+	}
+	(0..len)
+		.map(|_| decode_value(types, item, input))
+		.collect::<std::result::Result<_, _>>()
+		.map(Value::Array)
+}
+
+fn decode_primitive(
+	primitive: &TypeDefPrimitive,
+	input: &mut &[u8],
+) -> std::result::Result<Value, String> {
+	fn decode<T: Decode>(input: &mut &[u8]) -> std::result::Result<T, String> {
+		T::decode(input).map_err(codec_error)
+	}
+	Ok(match primitive {
+		TypeDefPrimitive::Bool => decode::<bool>(input)?.into(),
+		TypeDefPrimitive::Char => char::from_u32(decode::<u32>(input)?)
+			.ok_or("Invalid char.")?
+			.to_string()
+			.into(),
+		TypeDefPrimitive::Str => decode::<String>(input)?.into(),
+		TypeDefPrimitive::U8 => decode::<u8>(input)?.into(),
+		TypeDefPrimitive::U16 => decode::<u16>(input)?.into(),
+		TypeDefPrimitive::U32 => decode::<u32>(input)?.into(),
+		TypeDefPrimitive::U64 => decode::<u64>(input)?.into(),
+		TypeDefPrimitive::U128 => decode::<u128>(input)?.to_string().into(),
+		TypeDefPrimitive::I8 => decode::<i8>(input)?.into(),
+		TypeDefPrimitive::I16 => decode::<i16>(input)?.into(),
+		TypeDefPrimitive::I32 => decode::<i32>(input)?.into(),
+		TypeDefPrimitive::I64 => decode::<i64>(input)?.into(),
+		TypeDefPrimitive::I128 => decode::<i128>(input)?.to_string().into(),
+		TypeDefPrimitive::U256 | TypeDefPrimitive::I256 => hex(&decode::<[u8; 32]>(input)?).into(),
+	})
+}
+
+/// Whether `id` is `u128`, possibly wrapped in single-field structs such as `Perquintill`.
+fn is_u128(types: &PortableRegistry, id: u32) -> bool {
+	match types.resolve(id).map(|ty| ty.type_def()) {
+		Some(TypeDef::Primitive(TypeDefPrimitive::U128)) => true,
+		Some(TypeDef::Composite(composite)) => match composite.fields() {
+			[field] => is_u128(types, field.ty().id()),
+			_ => false,
+		},
+		_ => false,
+	}
+}
@@ -1,3 +1,7 @@
+pub mod blob_store;
 pub mod chain_spec;
+pub mod file_gateway;
+pub mod indexer;
+pub mod inspect;
 pub mod rpc;
 pub mod service;
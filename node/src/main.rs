@@ -1,6 +1,12 @@
 //! Substrate Node Template CLI library.
 #![warn(missing_docs)]
 
+mod blob_store;
+mod certificate;
+mod file_gateway;
+mod generate_spec;
+mod indexer;
+mod inspect;
 mod chain_spec;
 #[macro_use]
 mod service;
@@ -8,6 +14,7 @@ mod cli;
 mod command;
 mod command_helper;
 mod rpc;
+mod tx;
 
 fn main() -> sc_cli::Result<()> {
 	command::run()
@@ -7,12 +7,29 @@
 
 use std::sync::Arc;
 
-use jsonrpsee::RpcModule;
-use node_template_runtime::{opaque::Block, AccountId, Balance, Index};
+use crate::{
+	blob_store::BlobStore,
+	indexer::{EventFilter, IndexedEvent, Indexer, IndexerStatus, MAX_PAGE_SIZE},
+};
+use futures::channel::mpsc;
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+	RpcModule,
+};
+use node_template_runtime::{
+	opaque::Block, pallet_file_storage::File, AccountId, AssetId, Balance, BlockNumber, Hash,
+	Index, Runtime,
+};
+use pallet_file_storage_runtime_api::FileStorageApi;
+use sc_consensus_manual_seal::EngineCommand;
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
+use sp_core::Bytes;
+use sp_runtime::generic::BlockId;
 
 pub use sc_rpc_api::DenyUnsafe;
 
@@ -24,6 +41,12 @@ pub struct FullDeps<C, P> {
 	pub pool: Arc<P>,
 	/// Whether to deny unsafe calls
 	pub deny_unsafe: DenyUnsafe,
+	/// Channel to the manual-seal task, if blocks are sealed without Aura and GRANDPA.
+	pub command_sink: Option<mpsc::Sender<EngineCommand<Hash>>>,
+	/// Local store of file contents, if enabled.
+	pub blob_store: Option<Arc<BlobStore>>,
+	/// Index of the events of the custom pallets and balances, if enabled.
+	pub indexer: Option<Arc<Indexer>>,
 }
 
 /// Instantiate all full RPC extensions.
@@ -36,17 +59,38 @@ where
 	C: Send + Sync + 'static,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
+	C::Api: pallet_poe_rpc::PoeRuntimeApi<Block, AccountId, BlockNumber>,
+	C::Api: pallet_contracts_rpc::ContractsRuntimeApi<Block, AccountId, Balance, BlockNumber, Hash>,
+	C::Api: FileStorageApi<Block, AccountId, File<Runtime>, AssetId, Balance>,
 	C::Api: BlockBuilder<Block>,
 	P: TransactionPool + 'static,
 {
+	use pallet_contracts_rpc::{Contracts, ContractsApiServer};
+	use pallet_poe_rpc::{Poe, PoeApiServer};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
+	use sc_consensus_manual_seal::rpc::{ManualSeal, ManualSealApiServer};
 	use substrate_frame_rpc_system::{System, SystemApiServer};
 
 	let mut module = RpcModule::new(());
-	let FullDeps { client, pool, deny_unsafe } = deps;
+	let FullDeps { client, pool, deny_unsafe, command_sink, blob_store, indexer } = deps;
 
 	module.merge(System::new(client.clone(), pool.clone(), deny_unsafe).into_rpc())?;
-	module.merge(TransactionPayment::new(client).into_rpc())?;
+	module.merge(TransactionPayment::new(client.clone()).into_rpc())?;
+	module.merge(Contracts::new(client.clone()).into_rpc())?;
+	module.merge(Poe::new(client.clone()).into_rpc())?;
+
+	if let Some(store) = blob_store {
+		module.merge(Blobs { client, store, deny_unsafe }.into_rpc())?;
+	}
+
+	if let Some(indexer) = indexer {
+		module.merge(Events { indexer }.into_rpc())?;
+	}
+
+	if let Some(command_sink) = command_sink {
+		// `engine_createBlock` and `engine_finalizeBlock`.
+		module.merge(ManualSeal::new(command_sink).into_rpc())?;
+	}
 
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
@@ -55,3 +99,167 @@ where
 
 	Ok(module)
 }
+
+/// Upload and download of file contents to and from the local blob store.
+#[rpc(server)]
+pub trait BlobApi {
+	/// Store `data` and return its hash.
+	///
+	/// The BLAKE2-256 hash of `data` must be the `cid` of a file registered in the best block.
+	#[method(name = "blob_put")]
+	fn put(&self, data: Bytes) -> RpcResult<Hash>;
+
+	/// The contents of the file `cid`, if they are stored locally.
+	///
+	/// Unsafe, as it does not check who may download the file; the file gateway does.
+	#[method(name = "blob_get")]
+	fn get(&self, cid: Hash) -> RpcResult<Option<Bytes>>;
+}
+
+/// Error type of the blob RPC methods.
+pub enum BlobError {
+	/// The call to runtime failed.
+	RuntimeError,
+	/// No file is registered under the hash of the data.
+	NotRegistered,
+	/// Reading from or writing to the store failed.
+	StoreError,
+}
+
+impl From<BlobError> for i32 {
+	fn from(e: BlobError) -> i32 {
+		match e {
+			BlobError::RuntimeError => 1,
+			BlobError::NotRegistered => 2,
+			BlobError::StoreError => 3,
+		}
+	}
+}
+
+fn blob_error(e: BlobError, message: &str, data: impl std::fmt::Display) -> jsonrpsee::core::Error {
+	CallError::Custom(ErrorObject::owned(e.into(), message, Some(data.to_string()))).into()
+}
+
+/// Provides the blob RPC methods on top of a [`BlobStore`].
+struct Blobs<C> {
+	client: Arc<C>,
+	store: Arc<BlobStore>,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl<C> BlobApiServer for Blobs<C>
+where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: FileStorageApi<Block, AccountId, File<Runtime>, AssetId, Balance>,
+{
+	fn put(&self, data: Bytes) -> RpcResult<Hash> {
+		// Uploads take up disk space of the node.
+		self.deny_unsafe.check_if_safe()?;
+
+		let cid = BlobStore::hash_of(&data);
+		let at = BlockId::hash(self.client.info().best_hash);
+		let file = self
+			.client
+			.runtime_api()
+			.file(&at, cid)
+			.map_err(|e| blob_error(BlobError::RuntimeError, "Unable to query file.", e))?;
+		if file.is_none() {
+			return Err(blob_error(
+				BlobError::NotRegistered,
+				"No file is registered under the hash of the data.",
+				format!("{:?}", cid),
+			))
+		}
+
+		self.store
+			.put(&data)
+			.map_err(|e| blob_error(BlobError::StoreError, "Unable to store blob.", e))
+	}
+
+	fn get(&self, cid: Hash) -> RpcResult<Option<Bytes>> {
+		// Files for sale or without downloads must not be readable by anyone.
+		self.deny_unsafe.check_if_safe()?;
+
+		self.store
+			.get(&cid)
+			.map(|data| data.map(Into::into))
+			.map_err(|e| blob_error(BlobError::StoreError, "Unable to read blob.", e))
+	}
+}
+
+/// Queries of the event index.
+#[rpc(server)]
+pub trait IndexerApi {
+	/// Up to `limit` indexed events matching `filter`, in chain order, continuing after the event
+	/// `start_after` (block number and event index) if given.
+	///
+	/// Pass the block number and event index of the last event of a page as `start_after` to
+	/// fetch the next one. `limit` may be at most [`MAX_PAGE_SIZE`].
+	#[method(name = "indexer_events")]
+	fn events(
+		&self,
+		filter: EventFilter,
+		start_after: Option<(BlockNumber, u32)>,
+		limit: u32,
+	) -> RpcResult<Vec<IndexedEvent>>;
+
+	/// The last indexed block, the last indexed block that is finalized, and the number and the
+	/// first of the indexed blocks whose events could not be read.
+	#[method(name = "indexer_status")]
+	fn status(&self) -> RpcResult<IndexerStatus>;
+}
+
+/// Error type of the indexer RPC methods.
+pub enum IndexerError {
+	/// The requested page size is larger than [`MAX_PAGE_SIZE`].
+	PageTooLarge,
+	/// Reading from the database failed.
+	DatabaseError,
+}
+
+impl From<IndexerError> for i32 {
+	fn from(e: IndexerError) -> i32 {
+		match e {
+			IndexerError::PageTooLarge => 1,
+			IndexerError::DatabaseError => 2,
+		}
+	}
+}
+
+fn database_error(e: rusqlite::Error) -> jsonrpsee::core::Error {
+	CallError::Custom(ErrorObject::owned(
+		IndexerError::DatabaseError.into(),
+		"Unable to query the index.",
+		Some(e.to_string()),
+	))
+	.into()
+}
+
+/// Provides the indexer RPC methods on top of an [`Indexer`].
+struct Events {
+	indexer: Arc<Indexer>,
+}
+
+impl IndexerApiServer for Events {
+	fn events(
+		&self,
+		filter: EventFilter,
+		start_after: Option<(BlockNumber, u32)>,
+		limit: u32,
+	) -> RpcResult<Vec<IndexedEvent>> {
+		if limit > MAX_PAGE_SIZE {
+			return Err(CallError::Custom(ErrorObject::owned(
+				IndexerError::PageTooLarge.into(),
+				format!("Page size must be at most {}.", MAX_PAGE_SIZE),
+				None::<()>,
+			))
+			.into())
+		}
+
+		self.indexer.events(&filter, start_after, limit).map_err(database_error)
+	}
+
+	fn status(&self) -> RpcResult<IndexerStatus> {
+		self.indexer.status().map_err(database_error)
+	}
+}
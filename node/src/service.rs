@@ -1,15 +1,85 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
-use node_template_runtime::{self, opaque::Block, RuntimeApi};
-use sc_client_api::{BlockBackend, ExecutorProvider};
+use crate::{
+	blob_store::BlobStore,
+	inspect::{split_variant, MetadataCache},
+};
+use codec::Decode;
+use futures::{channel::mpsc, stream::BoxStream, StreamExt};
+use node_template_runtime::{self, opaque::Block, BlockNumber, Hash, RuntimeApi};
+use sc_client_api::{BlockBackend, BlockchainEvents, ExecutorProvider, StorageProvider};
 use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
+use sc_consensus_manual_seal::{
+	consensus::{aura::AuraConsensusDataProvider, timestamp::SlotTimestampProvider},
+	EngineCommand, ManualSealParams,
+};
 pub use sc_executor::NativeElseWasmExecutor;
+use remote_signer::RemoteKeystore;
 use sc_finality_grandpa::SharedVoterState;
-use sc_keystore::LocalKeystore;
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager};
 use sc_telemetry::{Telemetry, TelemetryWorker};
+use sp_blockchain::HeaderBackend;
 use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
-use std::{sync::Arc, time::Duration};
+use sp_core::{hashing::twox_128, storage::StorageKey};
+use sp_runtime::{generic::BlockId, traits::Header as HeaderT};
+use std::{
+	net::SocketAddr,
+	path::{Path, PathBuf},
+	str::FromStr,
+	sync::Arc,
+	time::Duration,
+};
+use substrate_prometheus_endpoint::{
+	register, CounterVec, Gauge, GaugeVec, Opts, PrometheusError, Registry, U64,
+};
+
+/// How blocks are sealed in place of Aura and GRANDPA.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Sealing {
+	/// Seal and finalize a block as soon as a transaction enters the pool.
+	Instant,
+	/// Seal blocks on `engine_createBlock` calls only.
+	Manual,
+	/// Seal and finalize a block at a fixed interval.
+	Interval(Duration),
+}
+
+impl FromStr for Sealing {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"instant" => Ok(Sealing::Instant),
+			"manual" => Ok(Sealing::Manual),
+			_ => {
+				let millis = s
+					.strip_prefix("interval=")
+					.ok_or_else(|| format!("expected instant, manual or interval=N, got {}", s))?
+					.parse::<u64>()
+					.map_err(|e| format!("invalid interval: {}", e))?;
+				if millis == 0 {
+					return Err("the interval must be at least 1 millisecond".into())
+				}
+				Ok(Sealing::Interval(Duration::from_millis(millis)))
+			},
+		}
+	}
+}
+
+/// Optional features of the node, set from the command line.
+#[derive(Debug, Clone, Default)]
+pub struct NodeOptions {
+	/// Seal blocks this way instead of with Aura and GRANDPA.
+	pub sealing: Option<Sealing>,
+	/// Directory to keep the contents of registered files in.
+	pub blob_store: Option<PathBuf>,
+	/// Address to serve the contents of registered files on over HTTP. Needs `blob_store`.
+	pub file_gateway: Option<SocketAddr>,
+	/// SQLite database to index the events of the custom pallets and balances in.
+	pub indexer: Option<PathBuf>,
+	/// File holding the token of the remote signer, if it listens for HTTP.
+	pub keystore_token_file: Option<PathBuf>,
+}
 
 // Our native executor instance.
 pub struct ExecutorDispatch;
@@ -36,8 +106,12 @@ pub(crate) type FullClient =
 type FullBackend = sc_service::TFullBackend<Block>;
 type FullSelectChain = sc_consensus::LongestChain<FullBackend, Block>;
 
+/// Builds the client, transaction pool and import queue shared by all services.
+///
+/// With `sealing`, the import queue accepts blocks sealed without Aura and GRANDPA.
 pub fn new_partial(
 	config: &Configuration,
+	sealing: Option<Sealing>,
 ) -> Result<
 	sc_service::PartialComponents<
 		FullClient,
@@ -58,10 +132,6 @@ pub fn new_partial(
 	>,
 	ServiceError,
 > {
-	if config.keystore_remote.is_some() {
-		return Err(ServiceError::Other("Remote Keystores are not supported.".into()))
-	}
-
 	let telemetry = config
 		.telemetry_endpoints
 		.clone()
@@ -110,9 +180,15 @@ pub fn new_partial(
 		telemetry.as_ref().map(|x| x.handle()),
 	)?;
 
-	let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
+	let import_queue = if sealing.is_some() {
+		sc_consensus_manual_seal::import_queue(
+			Box::new(client.clone()),
+			&task_manager.spawn_essential_handle(),
+			config.prometheus_registry(),
+		)
+	} else {
+		let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
 
-	let import_queue =
 		sc_consensus_aura::import_queue::<AuraPair, _, _, _, _, _, _>(ImportQueueParams {
 			block_import: grandpa_block_import.clone(),
 			justification_import: Some(Box::new(grandpa_block_import.clone())),
@@ -135,7 +211,8 @@ pub fn new_partial(
 			registry: config.prometheus_registry(),
 			check_for_equivocation: Default::default(),
 			telemetry: telemetry.as_ref().map(|x| x.handle()),
-		})?;
+		})?
+	};
 
 	Ok(sc_service::PartialComponents {
 		client,
@@ -149,15 +226,25 @@ pub fn new_partial(
 	})
 }
 
-fn remote_keystore(_url: &String) -> Result<Arc<LocalKeystore>, &'static str> {
-	// FIXME: here would the concrete keystore be built,
-	//        must return a concrete type (NOT `LocalKeystore`) that
-	//        implements `CryptoStore` and `SyncCryptoStore`
-	Err("Remote Keystore not supported.")
+/// The keystore of the signer process at `uri` (`--keystore-uri`), see [`RemoteKeystore`].
+///
+/// An HTTP signer is sent the token in `token_file` (`--keystore-token-file`).
+fn remote_keystore(uri: &str, token_file: Option<&Path>) -> Result<Arc<RemoteKeystore>, String> {
+	let token = token_file.map(remote_signer::read_token).transpose()?;
+	RemoteKeystore::new(uri, token).map(Arc::new)
 }
 
 /// Builds a new service for a full client.
-pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError> {
+///
+/// With `options.sealing`, blocks are sealed accordingly instead of by Aura and GRANDPA.
+pub fn new_full(
+	mut config: Configuration,
+	options: NodeOptions,
+) -> Result<TaskManager, ServiceError> {
+	if let Some(sealing) = options.sealing {
+		return new_manual_seal(config, sealing, options)
+	}
+
 	let sc_service::PartialComponents {
 		client,
 		backend,
@@ -167,10 +254,10 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		select_chain,
 		transaction_pool,
 		other: (block_import, grandpa_link, mut telemetry),
-	} = new_partial(&config)?;
+	} = new_partial(&config, None)?;
 
 	if let Some(url) = &config.keystore_remote {
-		match remote_keystore(url) {
+		match remote_keystore(url, options.keystore_token_file.as_deref()) {
 			Ok(k) => keystore_container.set_remote_keystore(k),
 			Err(e) =>
 				return Err(ServiceError::Other(format!(
@@ -220,14 +307,28 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 	let name = config.network.node_name.clone();
 	let enable_grandpa = !config.disable_grandpa;
 	let prometheus_registry = config.prometheus_registry().cloned();
+	let blob_store =
+		spawn_file_services(&options, &client, prometheus_registry.as_ref(), &task_manager)?;
+	spawn_pallet_metrics(prometheus_registry.as_ref(), client.clone(), &task_manager)?;
+	let indexer = options
+		.indexer
+		.as_deref()
+		.map(|path| crate::indexer::spawn(path, client.clone(), &task_manager))
+		.transpose()?;
 
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
 
 		Box::new(move |deny_unsafe, _| {
-			let deps =
-				crate::rpc::FullDeps { client: client.clone(), pool: pool.clone(), deny_unsafe };
+			let deps = crate::rpc::FullDeps {
+				client: client.clone(),
+				pool: pool.clone(),
+				deny_unsafe,
+				command_sink: None,
+				blob_store: blob_store.clone(),
+				indexer: indexer.clone(),
+			};
 			crate::rpc::create_full(deps).map_err(Into::into)
 		})
 	};
@@ -342,3 +443,339 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 	network_starter.start_network();
 	Ok(task_manager)
 }
+
+/// Builds a new service for a full client that seals blocks with `sealing` and finalizes them
+/// without GRANDPA.
+fn new_manual_seal(
+	config: Configuration,
+	sealing: Sealing,
+	options: NodeOptions,
+) -> Result<TaskManager, ServiceError> {
+	let sc_service::PartialComponents {
+		client,
+		backend,
+		mut task_manager,
+		import_queue,
+		keystore_container,
+		select_chain,
+		transaction_pool,
+		other: (_, _, mut telemetry),
+	} = new_partial(&config, Some(sealing))?;
+
+	let (network, system_rpc_tx, network_starter) =
+		sc_service::build_network(sc_service::BuildNetworkParams {
+			config: &config,
+			client: client.clone(),
+			transaction_pool: transaction_pool.clone(),
+			spawn_handle: task_manager.spawn_handle(),
+			import_queue,
+			block_announce_validator_builder: None,
+			warp_sync: None,
+		})?;
+
+	if config.offchain_worker.enabled {
+		sc_service::build_offchain_workers(
+			&config,
+			task_manager.spawn_handle(),
+			client.clone(),
+			network.clone(),
+		);
+	}
+
+	let prometheus_registry = config.prometheus_registry().cloned();
+	let blob_store =
+		spawn_file_services(&options, &client, prometheus_registry.as_ref(), &task_manager)?;
+	spawn_pallet_metrics(prometheus_registry.as_ref(), client.clone(), &task_manager)?;
+	let indexer = options
+		.indexer
+		.as_deref()
+		.map(|path| crate::indexer::spawn(path, client.clone(), &task_manager))
+		.transpose()?;
+
+	// Commands of `engine_createBlock` and `engine_finalizeBlock`.
+	let (command_sink, rpc_commands) = mpsc::channel(1024);
+
+	let rpc_extensions_builder = {
+		let client = client.clone();
+		let pool = transaction_pool.clone();
+
+		Box::new(move |deny_unsafe, _| {
+			let deps = crate::rpc::FullDeps {
+				client: client.clone(),
+				pool: pool.clone(),
+				deny_unsafe,
+				command_sink: Some(command_sink.clone()),
+				blob_store: blob_store.clone(),
+				indexer: indexer.clone(),
+			};
+			crate::rpc::create_full(deps).map_err(Into::into)
+		})
+	};
+
+	let _rpc_handlers = sc_service::spawn_tasks(sc_service::SpawnTasksParams {
+		network,
+		client: client.clone(),
+		keystore: keystore_container.sync_keystore(),
+		task_manager: &mut task_manager,
+		transaction_pool: transaction_pool.clone(),
+		rpc_builder: rpc_extensions_builder,
+		backend,
+		system_rpc_tx,
+		config,
+		telemetry: telemetry.as_mut(),
+	})?;
+
+	let commands_stream: BoxStream<'static, EngineCommand<Hash>> = match sealing {
+		Sealing::Manual => rpc_commands.boxed(),
+		Sealing::Instant => {
+			let imported = transaction_pool.pool().validated_pool().import_notification_stream();
+			futures::stream::select(rpc_commands, imported.map(|_| seal_and_finalize(false)))
+				.boxed()
+		},
+		Sealing::Interval(interval) => {
+			let ticks = futures::stream::unfold((), move |()| async move {
+				futures_timer::Delay::new(interval).await;
+				Some((seal_and_finalize(true), ()))
+			});
+			futures::stream::select(rpc_commands, ticks).boxed()
+		},
+	};
+
+	let proposer_factory = sc_basic_authorship::ProposerFactory::new(
+		task_manager.spawn_handle(),
+		client.clone(),
+		transaction_pool.clone(),
+		prometheus_registry.as_ref(),
+		telemetry.as_ref().map(|x| x.handle()),
+	);
+
+	let manual_seal = sc_consensus_manual_seal::run_manual_seal(ManualSealParams {
+		block_import: client.clone(),
+		env: proposer_factory,
+		client: client.clone(),
+		pool: transaction_pool,
+		commands_stream,
+		select_chain,
+		// The runtime still runs `pallet_aura`, which needs a slot in the digest of every block
+		// and a timestamp in that slot.
+		consensus_data_provider: Some(Box::new(AuraConsensusDataProvider::new(client.clone()))),
+		create_inherent_data_providers: move |_, ()| {
+			let client = client.clone();
+			async move {
+				let timestamp = SlotTimestampProvider::new_aura(client)
+					.map_err(|e| format!("failed to create timestamp provider: {:?}", e))?;
+				let slot =
+					sp_consensus_aura::inherents::InherentDataProvider::new(timestamp.slot());
+
+				Ok((timestamp, slot))
+			}
+		},
+	});
+
+	// the sealing task is considered essential, i.e. if it
+	// fails we take down the service with it.
+	task_manager.spawn_essential_handle().spawn_blocking(
+		"manual-seal",
+		Some("block-authoring"),
+		manual_seal,
+	);
+
+	network_starter.start_network();
+	Ok(task_manager)
+}
+
+/// Opens the blob store and starts the file gateway, as far as they are enabled in `options`.
+fn spawn_file_services(
+	options: &NodeOptions,
+	client: &Arc<FullClient>,
+	registry: Option<&Registry>,
+	task_manager: &TaskManager,
+) -> Result<Option<Arc<BlobStore>>, ServiceError> {
+	let blob_store = options
+		.blob_store
+		.clone()
+		.map(|dir| crate::blob_store::spawn(dir, client.clone(), task_manager))
+		.transpose()?;
+
+	if let Some(addr) = options.file_gateway {
+		let store = blob_store.clone().ok_or_else(|| {
+			ServiceError::Other("The file gateway needs a blob store (--blob-store).".into())
+		})?;
+		crate::file_gateway::spawn(addr, client.clone(), store, registry, task_manager)?;
+	}
+
+	Ok(blob_store)
+}
+
+/// Storage key of the value, or prefix of the map, `pallet::item`.
+pub(crate) fn storage_prefix(pallet: &[u8], item: &[u8]) -> StorageKey {
+	StorageKey([twox_128(pallet), twox_128(item)].concat())
+}
+
+/// Reads and decodes the storage value `pallet::item` at `at`.
+pub(crate) fn read_storage_value<T: Decode>(
+	client: &FullClient,
+	at: &BlockId<Block>,
+	pallet: &[u8],
+	item: &[u8],
+) -> Result<Option<T>, Box<dyn std::error::Error + Send + Sync>> {
+	let value = client.storage(at, &storage_prefix(pallet, item))?;
+	Ok(value.map(|data| T::decode(&mut &data.0[..])).transpose()?)
+}
+
+/// Pallets whose events are counted in the metrics.
+const METERED_PALLETS: [&str; 2] = ["FileStorage", "PoeModule"];
+
+/// Prometheus metrics of the file storage and PoE pallets.
+struct PalletMetrics {
+	files: Gauge<U64>,
+	claims: Gauge<U64>,
+	block_events: GaugeVec<U64>,
+	events: CounterVec<U64>,
+}
+
+impl PalletMetrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			files: register(
+				Gauge::new("file_storage_files", "Number of registered files (`FileCnt`).")?,
+				registry,
+			)?,
+			claims: register(
+				Gauge::new("poe_claims", "Number of PoE claims (`ClaimCount`).")?,
+				registry,
+			)?,
+			block_events: register(
+				GaugeVec::new(
+					Opts::new("pallet_block_events", "Events of a pallet in the best block."),
+					&["pallet"],
+				)?,
+				registry,
+			)?,
+			events: register(
+				CounterVec::new(
+					Opts::new("pallet_events_total", "Events of a pallet in finalized blocks."),
+					&["pallet", "event"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+
+	/// Updates the gauges from the new best block `hash`, whose events are decoded with
+	/// `metadata`.
+	fn update_best(&self, client: &FullClient, metadata: &mut MetadataCache, hash: Hash) {
+		let at = &BlockId::hash(hash);
+		match read_storage_value::<u64>(client, at, b"FileStorage", b"FileCnt") {
+			Ok(files) => self.files.set(files.unwrap_or_default()),
+			Err(e) => log::warn!(target: "metrics", "Failed to read FileCnt: {}", e),
+		}
+		match read_storage_value::<u64>(client, at, b"PoeModule", b"ClaimCount") {
+			Ok(claims) => self.claims.set(claims.unwrap_or_default()),
+			Err(e) => log::warn!(target: "metrics", "Failed to read ClaimCount: {}", e),
+		}
+
+		let mut block_events = [0; METERED_PALLETS.len()];
+		for (pallet, _) in metered_events(client, metadata, hash) {
+			if let Some(index) = METERED_PALLETS.iter().position(|p| *p == pallet) {
+				block_events[index] += 1;
+			}
+		}
+		for (pallet, count) in METERED_PALLETS.into_iter().zip(block_events) {
+			self.block_events.with_label_values(&[pallet]).set(count);
+		}
+	}
+
+	/// Counts the events of the finalized block `hash`.
+	fn count_finalized(&self, client: &FullClient, metadata: &mut MetadataCache, hash: Hash) {
+		for (pallet, name) in metered_events(client, metadata, hash) {
+			self.events.with_label_values(&[pallet, name]).inc();
+		}
+	}
+}
+
+/// The (pallet, event) names of the events of [`METERED_PALLETS`] in the block `hash`.
+fn metered_events(
+	client: &FullClient,
+	metadata: &mut MetadataCache,
+	hash: Hash,
+) -> Vec<(&'static str, &'static str)> {
+	let records = metadata.events(client, hash).unwrap_or_else(|e| {
+		log::warn!(target: "metrics", "Failed to read the events of {}: {}", hash, e);
+		Vec::new()
+	});
+	records
+		.iter()
+		.filter_map(|record| {
+			let (pallet, event) = split_variant(&record["event"]);
+			let (name, _) = split_variant(&event);
+			crate::indexer::indexed_event(&pallet, &name)
+		})
+		.filter(|(pallet, _)| METERED_PALLETS.contains(pallet))
+		.collect()
+}
+
+/// Registers the metrics of the custom pallets and spawns the task updating them on every new
+/// best and finalized block.
+///
+/// Events are only counted once finalized, so blocks replaced by a reorganization are never
+/// counted. Blocks finalized before the node started are not counted either.
+fn spawn_pallet_metrics(
+	registry: Option<&Registry>,
+	client: Arc<FullClient>,
+	task_manager: &TaskManager,
+) -> Result<(), ServiceError> {
+	let registry = match registry {
+		Some(registry) => registry,
+		None => return Ok(()),
+	};
+	let metrics = PalletMetrics::register(registry)?;
+
+	let task = async move {
+		enum Update {
+			Best(Hash),
+			Finalized(BlockNumber),
+		}
+
+		let best = client
+			.import_notification_stream()
+			.filter(|notification| futures::future::ready(notification.is_new_best))
+			.map(|notification| Update::Best(notification.hash));
+		let finalized = client
+			.finality_notification_stream()
+			.map(|notification| Update::Finalized(*notification.header.number()));
+		let mut updates = futures::stream::select(best, finalized);
+
+		let info = client.info();
+		let mut metadata = MetadataCache::default();
+		let mut counted = info.finalized_number;
+		metrics.update_best(&client, &mut metadata, info.best_hash);
+
+		while let Some(update) = updates.next().await {
+			match update {
+				Update::Best(hash) => metrics.update_best(&client, &mut metadata, hash),
+				// A notification may finalize several blocks at once.
+				Update::Finalized(number) =>
+					while counted < number {
+						let hash = match client.hash(counted + 1) {
+							Ok(Some(hash)) => hash,
+							Ok(None) => break,
+							Err(e) => {
+								log::warn!(target: "metrics", "Failed to read block hash: {}", e);
+								break
+							},
+						};
+						metrics.count_finalized(&client, &mut metadata, hash);
+						counted += 1;
+					},
+			}
+		}
+	};
+	task_manager.spawn_handle().spawn_blocking("pallet-metrics", None, task);
+	Ok(())
+}
+
+/// Command sealing a block on top of the best block and finalizing it right away.
+fn seal_and_finalize(create_empty: bool) -> EngineCommand<Hash> {
+	EngineCommand::SealNewBlock { create_empty, finalize: true, parent_hash: None, sender: None }
+}
@@ -0,0 +1,356 @@
+//! The `tx` subcommand.
+//!
+//! Signs a call of the proof-of-existence or file storage pallet with a secret URI or a keyring
+//! account, submits it to a node over WebSocket and waits until it is included in a block or
+//! finalized. The transaction carries the same `SignedExtra` as the benchmark extrinsics of
+//! [`crate::command_helper`], signed for the chain the node runs rather than the local runtime.
+//! The events the transaction emitted are printed, and a failed dispatch is reported as an error.
+
+use crate::{
+	command_helper::{create_signed_extrinsic, SigningContext},
+	service::storage_prefix,
+};
+use codec::{Decode, Encode};
+use frame_system::{EventRecord, Phase};
+use jsonrpsee::{
+	core::client::{ClientT, SubscriptionClientT},
+	rpc_params,
+	ws_client::{WsClient, WsClientBuilder},
+};
+use node_template_runtime::{
+	pallet_file_storage::{self, FileType},
+	pallet_poe, AccountId, Balance, Call, Event, Hash, Header, Runtime,
+};
+use sc_cli::{Result, RuntimeVersion};
+use sc_transaction_pool_api::TransactionStatus;
+use sp_core::{
+	crypto::Ss58Codec,
+	hashing::{blake2_256, twox_64},
+	sr25519,
+	storage::StorageKey,
+	Bytes, Pair,
+};
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::{generic::SignedBlock, traits::Header as HeaderT, OpaqueExtrinsic};
+use std::{fs, path::PathBuf};
+
+/// The `tx` command.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct TxCmd {
+	#[allow(missing_docs)]
+	#[clap(subcommand)]
+	pub call: TxCall,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub params: TxParams,
+}
+
+/// Where and how a transaction is signed and submitted.
+#[derive(Debug, Clone, clap::Args)]
+pub struct TxParams {
+	/// WebSocket RPC endpoint of the node to submit to.
+	#[clap(long, value_name = "URL", default_value = "ws://127.0.0.1:9944", global = true)]
+	pub url: String,
+
+	/// Secret URI of the sr25519 signing account, e.g. a mnemonic or `//Alice`.
+	#[clap(long, value_name = "SURI", conflicts_with = "keyring", global = true)]
+	pub suri: Option<String>,
+
+	/// Sign with a well-known development account: alice, bob, charlie, dave, eve or ferdie.
+	#[clap(long, value_name = "NAME", parse(try_from_str = parse_keyring), global = true)]
+	pub keyring: Option<Sr25519Keyring>,
+
+	/// Wait until the transaction is finalized instead of included in a block.
+	#[clap(long, global = true)]
+	pub finalized: bool,
+}
+
+/// The call to submit.
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum TxCall {
+	/// Proof-of-existence claims.
+	#[clap(subcommand)]
+	Poe(PoeCall),
+
+	/// Files for sale.
+	#[clap(subcommand)]
+	Files(FilesCall),
+}
+
+/// Calls of `PoeModule`.
+///
+/// A claim is given as a file, whose blake2-256 hash is the claim, or as the 0x-prefixed hash.
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum PoeCall {
+	/// Claim a file.
+	Claim {
+		/// The claimed file, or its hash.
+		#[clap(value_name = "CLAIM", parse(try_from_str = parse_claim))]
+		claim: Hash,
+	},
+
+	/// Revoke one of the signer's claims.
+	Revoke {
+		/// The claimed file, or its hash.
+		#[clap(value_name = "CLAIM", parse(try_from_str = parse_claim))]
+		claim: Hash,
+	},
+
+	/// Transfer one of the signer's claims to another account.
+	Transfer {
+		/// The claimed file, or its hash.
+		#[clap(value_name = "CLAIM", parse(try_from_str = parse_claim))]
+		claim: Hash,
+
+		/// The new owner.
+		#[clap(value_name = "DEST", parse(try_from_str = parse_account))]
+		dest: AccountId,
+	},
+}
+
+/// Calls of `FileStorage`.
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum FilesCall {
+	/// Register a file, identified by the blake2-256 hash of its contents.
+	Create {
+		/// The file, read to compute its cid and size.
+		#[clap(value_name = "FILE")]
+		file: PathBuf,
+
+		/// Where the file can be fetched from.
+		#[clap(long, value_name = "URL")]
+		link: String,
+
+		/// Put the file up for sale at this price, in the smallest unit of the currency.
+		#[clap(long, value_name = "AMOUNT")]
+		price: Option<Balance>,
+
+		/// Price the file in this asset instead of the native currency.
+		#[clap(long, value_name = "ID", requires = "price")]
+		asset: Option<u32>,
+
+		/// pdf, image, text, doc, audio, video or other.
+		#[clap(long = "type", value_name = "TYPE", parse(try_from_str = parse_file_type))]
+		file_type: Option<FileType>,
+
+		/// Let the file be downloaded through the node's file services.
+		#[clap(long)]
+		allow_download: bool,
+	},
+
+	/// Purchase a file.
+	Purchase {
+		/// The file's cid.
+		#[clap(value_name = "CID", parse(try_from_str = parse_hash))]
+		cid: Hash,
+
+		/// Pay at most this amount; defaults to the file's current price.
+		#[clap(long, value_name = "AMOUNT")]
+		bid: Option<Balance>,
+	},
+}
+
+fn parse_keyring(s: &str) -> std::result::Result<Sr25519Keyring, String> {
+	s.to_lowercase().parse().map_err(|_| format!("Unknown keyring account {}", s))
+}
+
+fn parse_hash(s: &str) -> std::result::Result<Hash, String> {
+	let bytes = sp_core::bytes::from_hex(s).map_err(|e| format!("{:?}", e))?;
+	if bytes.len() != 32 {
+		return Err("expected a 32 byte hash".into())
+	}
+	Ok(Hash::from_slice(&bytes))
+}
+
+fn parse_claim(s: &str) -> std::result::Result<Hash, String> {
+	if s.starts_with("0x") {
+		return parse_hash(s)
+	}
+	let data = fs::read(s).map_err(|e| format!("Reading {}: {}", s, e))?;
+	Ok(Hash(blake2_256(&data)))
+}
+
+fn parse_account(s: &str) -> std::result::Result<AccountId, String> {
+	AccountId::from_ss58check(s).map_err(|e| format!("{:?}", e))
+}
+
+fn parse_file_type(s: &str) -> std::result::Result<FileType, String> {
+	Ok(match s.to_lowercase().as_str() {
+		"pdf" => FileType::Pdf,
+		"image" => FileType::Image,
+		"text" => FileType::Text,
+		"doc" => FileType::Doc,
+		"audio" => FileType::Audio,
+		"video" => FileType::Video,
+		"other" => FileType::Other,
+		_ => return Err(format!("Unknown file type {}", s)),
+	})
+}
+
+fn rpc_error(e: jsonrpsee::core::Error) -> sc_cli::Error {
+	format!("RPC request failed: {}", e).into()
+}
+
+impl TxCmd {
+	/// Run the command.
+	pub fn run(&self) -> Result<()> {
+		let signer = match (&self.params.suri, self.params.keyring) {
+			(Some(suri), _) => sr25519::Pair::from_string(suri, None)
+				.map_err(|e| format!("Invalid secret URI: {:?}", e))?,
+			(None, Some(keyring)) => keyring.pair(),
+			(None, None) => return Err("Either --suri or --keyring is required.".into()),
+		};
+		tokio::runtime::Builder::new_current_thread()
+			.enable_all()
+			.build()?
+			.block_on(self.submit(signer))
+	}
+
+	async fn submit(&self, signer: sr25519::Pair) -> Result<()> {
+		let client = WsClientBuilder::default().build(&self.params.url).await.map_err(rpc_error)?;
+		let call = self.call.to_call(&client).await?;
+
+		let account = AccountId::from(signer.public());
+		let nonce: u32 = client
+			.request("system_accountNextIndex", rpc_params![account.to_ss58check()])
+			.await
+			.map_err(rpc_error)?;
+		let genesis_hash: Option<Hash> =
+			client.request("chain_getBlockHash", rpc_params![0]).await.map_err(rpc_error)?;
+		let best: Option<Header> =
+			client.request("chain_getHeader", None).await.map_err(rpc_error)?;
+		let version: RuntimeVersion =
+			client.request("state_getRuntimeVersion", None).await.map_err(rpc_error)?;
+		let (genesis_hash, best) = genesis_hash.zip(best).ok_or("The node has no blocks.")?;
+		let context = SigningContext {
+			genesis_hash,
+			best_hash: best.hash(),
+			best_number: *best.number(),
+			spec_version: version.spec_version,
+			transaction_version: version.transaction_version,
+		};
+		let extrinsic = create_signed_extrinsic(&signer, call, nonce, &context).encode();
+
+		let mut statuses = client
+			.subscribe::<TransactionStatus<Hash, Hash>>(
+				"author_submitAndWatchExtrinsic",
+				rpc_params![Bytes(extrinsic.clone())],
+				"author_unwatchExtrinsic",
+			)
+			.await
+			.map_err(rpc_error)?;
+		eprintln!("Submitted as {} with nonce {}", account, nonce);
+		while let Some(status) = statuses.next().await {
+			match status.map_err(rpc_error)? {
+				TransactionStatus::InBlock(hash) if !self.params.finalized =>
+					return report(&client, hash, &extrinsic).await,
+				TransactionStatus::InBlock(hash) => eprintln!("In block {:?}", hash),
+				TransactionStatus::Finalized(hash) =>
+					return report(&client, hash, &extrinsic).await,
+				TransactionStatus::Retracted(hash) => eprintln!("Block {:?} was retracted", hash),
+				status @ (TransactionStatus::FinalityTimeout(_) |
+				TransactionStatus::Usurped(_) |
+				TransactionStatus::Dropped |
+				TransactionStatus::Invalid) =>
+					return Err(format!("Transaction not included: {:?}", status).into()),
+				_ => {},
+			}
+		}
+		Err("The node closed the subscription.".into())
+	}
+}
+
+impl TxCall {
+	/// The runtime call, reading the chain for values not given on the command line.
+	async fn to_call(&self, client: &WsClient) -> Result<Call> {
+		Ok(match self {
+			TxCall::Poe(PoeCall::Claim { claim }) =>
+				pallet_poe::Call::create_claim { claim: *claim }.into(),
+			TxCall::Poe(PoeCall::Revoke { claim }) =>
+				pallet_poe::Call::revoke_claim { claim: *claim }.into(),
+			TxCall::Poe(PoeCall::Transfer { claim, dest }) =>
+				pallet_poe::Call::transfer_claim { claim: *claim, dest: dest.clone() }.into(),
+			TxCall::Files(FilesCall::Create {
+				file,
+				link,
+				price,
+				asset,
+				file_type,
+				allow_download,
+			}) => {
+				let data =
+					fs::read(file).map_err(|e| format!("Reading {}: {}", file.display(), e))?;
+				let file_size = u32::try_from(data.len()).map_err(|_| "The file is too large.")?;
+				pallet_file_storage::Call::create_file {
+					cid: Hash(blake2_256(&data)),
+					cost: *price,
+					asset_id: *asset,
+					file_type: file_type.clone(),
+					file_link: link.clone().into_bytes(),
+					allow_download: *allow_download,
+					file_size,
+				}
+				.into()
+			},
+			TxCall::Files(FilesCall::Purchase { cid, bid }) => {
+				let bid_price = match bid {
+					Some(bid) => *bid,
+					None => current_price(client, cid).await?,
+				};
+				pallet_file_storage::Call::purchase_file { cid: *cid, bid_price }.into()
+			},
+		})
+	}
+}
+
+/// The price of the file `cid` at the node's best block.
+async fn current_price(client: &WsClient, cid: &Hash) -> Result<Balance> {
+	let mut key = storage_prefix(b"FileStorage", b"Files").0;
+	key.extend(twox_64(&cid.encode()));
+	key.extend(cid.encode());
+	let file: Option<Bytes> = client
+		.request("state_getStorage", rpc_params![StorageKey(key)])
+		.await
+		.map_err(rpc_error)?;
+	let file = file.ok_or_else(|| format!("No file {:?}.", cid))?;
+	let file = pallet_file_storage::File::<Runtime>::decode(&mut &file[..])
+		.map_err(|e| format!("Decoding file {:?}: {}", cid, e))?;
+	file.price.ok_or_else(|| format!("File {:?} is not for sale.", cid).into())
+}
+
+/// Print the events `extrinsic` emitted in block `hash`, failing if its dispatch failed.
+async fn report(client: &WsClient, hash: Hash, extrinsic: &[u8]) -> Result<()> {
+	let block: Option<SignedBlock<node_template_runtime::opaque::Block>> =
+		client.request("chain_getBlock", rpc_params![hash]).await.map_err(rpc_error)?;
+	let block = block.ok_or_else(|| format!("Unknown block {:?}.", hash))?;
+	let index = block
+		.block
+		.extrinsics
+		.iter()
+		.position(|xt: &OpaqueExtrinsic| xt.encode() == extrinsic)
+		.ok_or_else(|| format!("Block {:?} does not contain the transaction.", hash))?;
+	println!("Included in block {:?} at index {}", hash, index);
+
+	let events: Option<Bytes> = client
+		.request("state_getStorage", rpc_params![storage_prefix(b"System", b"Events"), hash])
+		.await
+		.map_err(rpc_error)?;
+	let events = match events {
+		Some(events) => Vec::<EventRecord<Event, Hash>>::decode(&mut &events[..])
+			.map_err(|e| format!("Decoding events: {}", e))?,
+		None => Vec::new(),
+	};
+	for record in events {
+		if record.phase != Phase::ApplyExtrinsic(index as u32) {
+			continue
+		}
+		println!("{:?}", record.event);
+		if let Event::System(frame_system::Event::ExtrinsicFailed { dispatch_error, .. }) =
+			record.event
+		{
+			return Err(format!("Dispatch failed: {:?}", dispatch_error).into())
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,30 @@
+//! Runtime API definition for the file storage pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_runtime::traits::Block as BlockT;
+
+sp_api::decl_runtime_apis! {
+	/// Queries against `pallet_file_storage`.
+	///
+	/// `File` is the runtime's `pallet_file_storage::File`, which carries the owner, price,
+	/// block number and time of creation of a file.
+	#[api_version(2)]
+	pub trait FileStorageApi<AccountId, File, AssetId, Balance> where
+		AccountId: Codec,
+		File: Codec,
+		AssetId: Codec,
+		Balance: Codec,
+	{
+		/// The file registered under `cid`, if any.
+		fn file(cid: <Block as BlockT>::Hash) -> Option<File>;
+
+		/// Price of the file `cid` and the asset it is priced in, `None` meaning the native
+		/// currency, if the file exists and is for sale.
+		fn listing(cid: <Block as BlockT>::Hash) -> Option<(Option<AssetId>, Balance)>;
+
+		/// Whether `who` may download the contents of the file `cid`.
+		fn can_download(cid: <Block as BlockT>::Hash, who: AccountId) -> bool;
+	}
+}
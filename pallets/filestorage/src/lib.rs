@@ -4,7 +4,13 @@
 // Re-export pallet items so that they can be accessed from the crate namespace.
 pub use pallet::*;
 
+pub mod migrations;
 
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
 
 #[frame_support::pallet]
 pub mod pallet {
@@ -15,8 +21,12 @@ pub mod pallet {
   use scale_info::prelude::string::String;
 	use frame_support::{
 		inherent::Vec,
-		sp_runtime::traits::Hash,
-		traits::{tokens::ExistenceRequirement, Currency, Randomness},
+		sp_runtime::traits::{Hash, Zero},
+		traits::{
+			fungibles::{self, Transfer as _},
+			tokens::ExistenceRequirement,
+			Currency, Randomness, UnixTime,
+		},
 		transactional,
 	};
 	use scale_info::TypeInfo;
@@ -28,8 +38,9 @@ pub mod pallet {
 	use frame_support::serde::{Deserialize, Serialize};
 
 	type AccountOf<T> = <T as frame_system::Config>::AccountId;
-	type BalanceOf<T> =
+	pub type BalanceOf<T> =
 		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+	pub type AssetIdOf<T> = <<T as Config>::Assets as fungibles::Inspect<AccountOf<T>>>::AssetId;
 
 
   // Struct for holding File information.
@@ -37,11 +48,19 @@ pub mod pallet {
 	#[scale_info(skip_type_params(T))]
 	pub struct File<T: Config> {
 		pub price: Option<BalanceOf<T>>,
+		/// Asset `price` is denominated in, or `None` for the native currency.
+		pub asset_id: Option<AssetIdOf<T>>,
 		pub owner: AccountOf<T>,
 		pub file_type : FileType,
 		pub file_link: BoundedVec<u8, T::MaxLength>,
 		pub allow_download :bool,
 		pub file_size : u32,
+		/// Block number at which the file was created.
+		pub block_number: T::BlockNumber,
+		/// Time at which the file was created, in milliseconds since the Unix epoch.
+		///
+		/// Files created before times were recorded carry a time of 0.
+		pub timestamp: u64,
 	}
 
   // Set Gender type in Kitty struct.
@@ -79,8 +98,12 @@ pub mod pallet {
   // }
   
 
+  /// The current storage version.
+  const STORAGE_VERSION: StorageVersion = StorageVersion::new(3);
+
   #[pallet::pallet]
   #[pallet::generate_store(pub(super) trait Store)]
+  #[pallet::storage_version(STORAGE_VERSION)]
   pub struct Pallet<T>(_);
 
   /// Configure the pallet by specifying the parameters and types on which it depends.
@@ -92,19 +115,38 @@ pub mod pallet {
     /// The Currency handler for the Kitties pallet.
 	type Currency: Currency<Self::AccountId>;
 
-	/// The maximum amount of Kitties a single account can own.
+	/// Fungible assets files can be priced in besides the native currency.
+	type Assets: fungibles::Transfer<Self::AccountId, Balance = BalanceOf<Self>>;
+
+	/// Hard cap on the number of files a single account can own, which bounds `FilesOwned`.
 	#[pallet::constant]
 	type MaxFileOwned: Get<u32>;
+	/// The number of files a single account can own, at most `MaxFileOwned`.
+	///
+	/// May change at runtime, e.g. through on-chain parameters.
+	type FileOwnedLimit: Get<u32>;
 
-	/// The minimum length a file_link may be.
-	#[pallet::constant]
+	/// The minimum length a file_link may be. May change at runtime.
 	type MinLength: Get<u32>;
-	/// The maximum length a file_link may be.
+	/// Hard cap on the length of a file_link, which bounds its storage.
 	#[pallet::constant]
 	type MaxLength: Get<u32>;
+	/// The maximum length a file_link may be, at most `MaxLength`. May change at runtime.
+	type LinkLengthLimit: Get<u32>;
 
 	/// The type of Randomness we want to specify for this pallet.
 	type KittyRandomness: Randomness<Self::Hash, Self::BlockNumber>;
+
+	/// Source of the wall-clock time recorded with each file.
+	type TimeProvider: UnixTime;
+
+	/// Origin allowed to take down any file, e.g. root or the council.
+	type ForceOrigin: EnsureOrigin<Self::Origin>;
+
+	/// The most purchases of a file `force_remove_file` clears itself. Any others are cleared in
+	/// later blocks, with their spare weight.
+	#[pallet::constant]
+	type MaxPurchasesRemoved: Get<u32>;
   }
   
   
@@ -119,6 +161,16 @@ pub mod pallet {
     ClaimRevoked { who: T::AccountId, claim: T::Hash },
     ///Event emitted when a file is uploaded 
     FileCreated { who: T::AccountId, cid: T::Hash },
+    /// Event emitted when a file is taken down by `ForceOrigin`.
+    FileRemoved { owner: T::AccountId, cid: T::Hash },
+    /// Event emitted when a file is purchased. `asset_id` is `None` for the native currency.
+    FilePurchased {
+      buyer: T::AccountId,
+      seller: T::AccountId,
+      cid: T::Hash,
+      asset_id: Option<AssetIdOf<T>>,
+      price: BalanceOf<T>,
+    },
   }
   
   
@@ -138,7 +190,7 @@ pub mod pallet {
     NotClaimOwner,
     /// Handles arithemtic overflow when incrementing the Kitty counter.
 		KittyCntOverflow,
-		/// An account cannot own more Kitties than `MaxKittyCount`.
+		/// An account cannot own more files than `FileOwnedLimit`.
 		ExceedMaxFileOwned,
 		/// Buyer cannot be the owner.
 		BuyerIsKittyOwner,
@@ -154,6 +206,22 @@ pub mod pallet {
 		KittyBidPriceTooLow,
 		/// Ensures that an account has enough funds to purchase a Kitty.
 		NotEnoughBalance,
+		/// No file is registered under the given cid.
+		FileNotExist,
+		/// The file has no price, so it cannot be purchased.
+		FileNotForSale,
+		/// The owner of a file cannot purchase it.
+		BuyerIsFileOwner,
+		/// The account already purchased the file.
+		AlreadyPurchased,
+		/// The price of the file is above the buyer's bid.
+		BidPriceTooLow,
+		/// A file priced in an asset must have a price.
+		AssetWithoutPrice,
+		/// The asset a file is priced in does not exist.
+		AssetNotExist,
+		/// The purchases of a file taken down under the same cid are still being cleared.
+		PurchasesBeingCleared,
   }
 
   
@@ -189,6 +257,24 @@ pub mod pallet {
 		ValueQuery,
 	>;
 
+	#[pallet::storage]
+	#[pallet::getter(fn purchases)]
+	/// Accounts that purchased a file, keyed by the file's cid.
+	pub(super) type Purchases<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		T::Hash,
+		Blake2_128Concat,
+		T::AccountId,
+		(),
+	>;
+
+	#[pallet::storage]
+	/// Files taken down by `force_remove_file` whose purchases are still being cleared, with the
+	/// block they were taken down in.
+	pub(super) type PurgingPurchases<T: Config> =
+		StorageMap<_, Twox64Concat, T::Hash, T::BlockNumber>;
+
 	#[pallet::storage]
 	#[pallet::getter(fn cnt_file_downloaded)]
 	/// Keeps track of what accounts own what Kitty.
@@ -200,16 +286,106 @@ pub mod pallet {
 		ValueQuery,
 	>;
 
+	/// A file present from the genesis block, priced in the native currency if at all.
+	#[derive(Clone, PartialEq, RuntimeDebug)]
+	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+	#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+	pub struct GenesisFile<AccountId, Hash, Balance> {
+		pub cid: Hash,
+		pub owner: AccountId,
+		pub file_link: Vec<u8>,
+		pub file_type: FileType,
+		pub allow_download: bool,
+		pub file_size: u32,
+		pub price: Option<Balance>,
+	}
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		pub files: Vec<GenesisFile<T::AccountId, T::Hash, BalanceOf<T>>>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { files: Vec::new() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			for file in &self.files {
+				assert!(!Files::<T>::contains_key(&file.cid), "Duplicate file in genesis.");
+				let file_link: BoundedVec<u8, T::MaxLength> = file
+					.file_link
+					.clone()
+					.try_into()
+					.expect("Genesis file link exceeds MaxLength.");
+				FilesOwned::<T>::mutate(&file.owner, |owned| owned.try_push(file.cid))
+					.expect("Genesis files exceed MaxFileOwned for an owner.");
+				let genesis_file = File::<T> {
+					price: file.price,
+					asset_id: None,
+					owner: file.owner.clone(),
+					file_type: file.file_type.clone(),
+					file_link,
+					allow_download: file.allow_download,
+					file_size: file.file_size,
+					block_number: T::BlockNumber::default(),
+					timestamp: 0,
+				};
+				Files::<T>::insert(&file.cid, genesis_file);
+			}
+			FileCnt::<T>::put(self.files.len() as u64);
+		}
+	}
+
+  #[pallet::hooks]
+  impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		/// Clear the purchases `force_remove_file` left behind, as far as the spare weight of the
+		/// block allows.
+		fn on_idle(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			let db = T::DbWeight::get();
+			// Reading the next file and removing it once its purchases are cleared.
+			let overhead = db.reads_writes(1, 1);
+			if remaining_weight <= overhead.saturating_add(db.writes(1)) {
+				return 0
+			}
+
+			let (cid, removed_at) = match <PurgingPurchases<T>>::iter().next() {
+				Some(entry) => entry,
+				None => return db.reads(1),
+			};
+			// Purchases cleared earlier in the same block would be counted again.
+			if removed_at == now {
+				return db.reads(1)
+			}
+
+			let limit = (remaining_weight - overhead) / db.writes(1).max(1);
+			let limit = limit.min(T::MaxPurchasesRemoved::get().into()) as u32;
+			let result = <Purchases<T>>::clear_prefix(&cid, limit, None);
+			if result.maybe_cursor.is_none() {
+				<PurgingPurchases<T>>::remove(&cid);
+			}
+			overhead.saturating_add(db.writes(result.unique.into()))
+		}
+  }
+
   #[pallet::call]
   impl<T: Config> Pallet<T> {
 
 
     /// Upload File and sets its properties and updates storage.
-		#[pallet::weight(100)]
+		// Reads the file, its purging purchases, the asset's details for an asset, the two link
+		// length parameters, the owner's files, the file count and the time, and writes the file,
+		// the owner's files and the file count.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(8, 3))]
 		pub fn create_file(
 			origin: OriginFor<T>,
 			cid: T::Hash,
 			cost: Option<BalanceOf<T>>,
+			asset_id: Option<AssetIdOf<T>>,
 			file_type: Option<FileType>,
 			file_link: Vec<u8>,
 			allow_download :bool,
@@ -221,20 +397,43 @@ pub mod pallet {
 			// ensure!(Self::is_kitty_owner(&kitty_id, &sender)?, <Error<T>>::NotKittyOwner);
       //Action: checking if file already created
       ensure!(!Files::<T>::contains_key(&cid), Error::<T>::AlreadyUploaded);
-
+      ensure!(!PurgingPurchases::<T>::contains_key(&cid), Error::<T>::PurchasesBeingCleared);
+
+	  if let Some(asset_id) = asset_id {
+		ensure!(cost.is_some(), Error::<T>::AssetWithoutPrice);
+		// Assets cannot be created with a minimum balance of zero, so only missing ones have it.
+		ensure!(
+			!<T::Assets as fungibles::Inspect<_>>::minimum_balance(asset_id).is_zero(),
+			Error::<T>::AssetNotExist
+		);
+	  }
+
+	  ensure!(file_link.len() <= T::LinkLengthLimit::get() as usize, Error::<T>::LinkTooLong);
 	  let bounded_file_link: BoundedVec<_, _> =
 				file_link.try_into().map_err(|()| Error::<T>::LinkTooLong)?;
 	  ensure!(bounded_file_link.len() >= T::MinLength::get() as usize, Error::<T>::LinkTooShort);
 
+	  <FilesOwned<T>>::try_mutate(&sender, |owned| -> DispatchResult {
+		ensure!(
+			owned.len() < T::FileOwnedLimit::get() as usize,
+			Error::<T>::ExceedMaxFileOwned
+		);
+		owned.try_push(cid).map_err(|_| Error::<T>::ExceedMaxFileOwned)?;
+		Ok(())
+	  })?;
+
 
     //   create File data
       let file = File::<T> {
         price: cost.clone(),
+        asset_id,
         file_type: file_type.unwrap_or_else(|| FileType::Other),
         owner: sender.clone(),
 		file_link: bounded_file_link,
 		allow_download,
 		file_size,
+		block_number: <frame_system::Pallet<T>>::block_number(),
+		timestamp: T::TimeProvider::now().as_millis() as u64,
       };
 
 			// let mut kitty = Self::kitties(&kitty_id).ok_or(<Error<T>>::KittyNotExist)?;
@@ -254,5 +453,92 @@ pub mod pallet {
       Self::deposit_event(Event::FileCreated { who: sender, cid });
 			Ok(())
 		}
+
+		/// Take down a file regardless of its owner.
+		///
+		/// Must be called by `ForceOrigin`, i.e. through governance. Clears up to
+		/// `MaxPurchasesRemoved` purchases of the file, and the rest in later blocks.
+		#[pallet::weight(
+			10_000 + T::DbWeight::get().reads_writes(3, 5) +
+				T::DbWeight::get().writes(T::MaxPurchasesRemoved::get().into())
+		)]
+		pub fn force_remove_file(origin: OriginFor<T>, cid: T::Hash) -> DispatchResult {
+			T::ForceOrigin::ensure_origin(origin)?;
+
+			let file = <Files<T>>::take(&cid).ok_or(Error::<T>::FileNotExist)?;
+			<FilesOwned<T>>::mutate(&file.owner, |owned| owned.retain(|owned| *owned != cid));
+			<CntFileDownloaded<T>>::remove(&cid);
+			let result = <Purchases<T>>::clear_prefix(&cid, T::MaxPurchasesRemoved::get(), None);
+			if result.maybe_cursor.is_some() {
+				<PurgingPurchases<T>>::insert(&cid, <frame_system::Pallet<T>>::block_number());
+			}
+			<FileCnt<T>>::mutate(|cnt| *cnt = cnt.saturating_sub(1));
+
+			Self::deposit_event(Event::FileRemoved { owner: file.owner, cid });
+			Ok(())
+		}
+
+		/// Purchase a file at its listed price, paid to its owner in the file's asset.
+		///
+		/// The purchase fails if the price is above `bid_price`.
+		// Reads the file and purchase, and reads and writes both accounts, and the asset's details
+		// for an asset, besides recording the purchase.
+		#[pallet::weight(50_000_000 + T::DbWeight::get().reads_writes(5, 4))]
+		#[transactional]
+		pub fn purchase_file(
+			origin: OriginFor<T>,
+			cid: T::Hash,
+			bid_price: BalanceOf<T>,
+		) -> DispatchResult {
+			let buyer = ensure_signed(origin)?;
+
+			let file = Self::files(&cid).ok_or(Error::<T>::FileNotExist)?;
+			let price = file.price.ok_or(Error::<T>::FileNotForSale)?;
+			ensure!(buyer != file.owner, Error::<T>::BuyerIsFileOwner);
+			ensure!(!<Purchases<T>>::contains_key(&cid, &buyer), Error::<T>::AlreadyPurchased);
+			ensure!(price <= bid_price, Error::<T>::BidPriceTooLow);
+
+			match file.asset_id {
+				Some(asset_id) => {
+					T::Assets::transfer(asset_id, &buyer, &file.owner, price, true)?;
+				},
+				None => {
+					let keep_alive = ExistenceRequirement::KeepAlive;
+					T::Currency::transfer(&buyer, &file.owner, price, keep_alive)?;
+				},
+			}
+			<Purchases<T>>::insert(&cid, &buyer, ());
+
+			Self::deposit_event(Event::FilePurchased {
+				buyer,
+				seller: file.owner,
+				cid,
+				asset_id: file.asset_id,
+				price,
+			});
+			Ok(())
+		}
+  }
+
+  impl<T: Config> Pallet<T> {
+		/// Whether `who` purchased the file `cid`.
+		pub fn has_purchased(cid: &T::Hash, who: &T::AccountId) -> bool {
+			<Purchases<T>>::contains_key(cid, who)
+		}
+
+		/// Whether `who` may download the contents of the file `cid`.
+		///
+		/// The owner always may. Anyone else only if the file allows downloads and is either not
+		/// for sale or was purchased by `who`. This is the one definition of access to a file,
+		/// used by the file gateway and by contracts alike.
+		pub fn can_download(cid: &T::Hash, who: &T::AccountId) -> bool {
+			match <Files<T>>::get(cid) {
+				Some(file) if file.owner == *who => true,
+				Some(file) =>
+					file.allow_download &&
+						(file.price.is_none() || Self::has_purchased(cid, who)),
+				None => false,
+			}
+		}
   }
-}
\ No newline at end of file
+}
@@ -0,0 +1,263 @@
+//! Storage migrations for the file storage pallet.
+
+/// Version 1: files record the block number and time of their creation.
+pub mod v1 {
+	use crate::pallet::{BalanceOf, Config, FileType, Pallet};
+	use codec::{Decode, Encode};
+	use core::marker::PhantomData;
+	use frame_support::{
+		sp_runtime::traits::Zero,
+		traits::{Get, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+		weights::Weight,
+		BoundedVec, Twox64Concat,
+	};
+	#[cfg(feature = "try-runtime")]
+	use frame_support::traits::OnRuntimeUpgradeHelpersExt;
+
+	const LOG_TARGET: &str = "runtime::file-storage";
+
+	/// A file as stored before version 1.
+	#[derive(Encode, Decode)]
+	struct OldFile<T: Config> {
+		price: Option<BalanceOf<T>>,
+		owner: T::AccountId,
+		file_type: FileType,
+		file_link: BoundedVec<u8, T::MaxLength>,
+		allow_download: bool,
+		file_size: u32,
+	}
+
+	/// A file as stored in version 1.
+	#[derive(Encode, Decode)]
+	pub(crate) struct FileV1<T: Config> {
+		pub(crate) price: Option<BalanceOf<T>>,
+		pub(crate) owner: T::AccountId,
+		pub(crate) file_type: FileType,
+		pub(crate) file_link: BoundedVec<u8, T::MaxLength>,
+		pub(crate) allow_download: bool,
+		pub(crate) file_size: u32,
+		pub(crate) block_number: T::BlockNumber,
+		pub(crate) timestamp: u64,
+	}
+
+	/// `Files` as it was typed in version 1.
+	#[frame_support::storage_alias]
+	type Files<T: Config> =
+		StorageMap<Pallet<T>, Twox64Concat, <T as frame_system::Config>::Hash, FileV1<T>>;
+
+	/// Extends every `Files` entry with a creation block and time of 0, since neither is known
+	/// for existing files.
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() >= 1 {
+				log::info!(target: LOG_TARGET, "MigrateToV1 already applied, skipping");
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut translated = 0u64;
+			Files::<T>::translate::<OldFile<T>, _>(|_, old| {
+				translated += 1;
+				Some(FileV1::<T> {
+					price: old.price,
+					owner: old.owner,
+					file_type: old.file_type,
+					file_link: old.file_link,
+					allow_download: old.allow_download,
+					file_size: old.file_size,
+					block_number: Zero::zero(),
+					timestamp: 0,
+				})
+			});
+			StorageVersion::new(1).put::<Pallet<T>>();
+
+			log::info!(target: LOG_TARGET, "MigrateToV1 translated {} files", translated);
+			T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<(), &'static str> {
+			Self::set_temp_storage(Files::<T>::iter_keys().count() as u32, "file_storage_v1_files");
+			Ok(())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			// Later migrations may have changed the layout of `Files` again, so only the number
+			// of entries is checked here.
+			let files: u32 =
+				Self::get_temp_storage("file_storage_v1_files").ok_or("no file count")?;
+			frame_support::ensure!(
+				Pallet::<T>::on_chain_storage_version() >= 1,
+				"storage version not updated"
+			);
+			frame_support::ensure!(
+				Files::<T>::iter_keys().count() as u32 == files,
+				"files were lost"
+			);
+			Ok(())
+		}
+	}
+}
+
+/// Version 2: files may be priced in a fungible asset instead of the native currency.
+pub mod v2 {
+	use super::v1::FileV1;
+	use crate::pallet::{Config, File, Files, Pallet};
+	use core::marker::PhantomData;
+	use frame_support::{
+		traits::{Get, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+		weights::Weight,
+	};
+	#[cfg(feature = "try-runtime")]
+	use frame_support::traits::OnRuntimeUpgradeHelpersExt;
+
+	const LOG_TARGET: &str = "runtime::file-storage";
+
+	/// Prices every existing file in the native currency.
+	pub struct MigrateToV2<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV2<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 1 {
+				log::info!(target: LOG_TARGET, "MigrateToV2 not applicable, skipping");
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut translated = 0u64;
+			Files::<T>::translate::<FileV1<T>, _>(|_, old| {
+				translated += 1;
+				Some(File {
+					price: old.price,
+					asset_id: None,
+					owner: old.owner,
+					file_type: old.file_type,
+					file_link: old.file_link,
+					allow_download: old.allow_download,
+					file_size: old.file_size,
+					block_number: old.block_number,
+					timestamp: old.timestamp,
+				})
+			});
+			StorageVersion::new(2).put::<Pallet<T>>();
+
+			log::info!(target: LOG_TARGET, "MigrateToV2 translated {} files", translated);
+			T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<(), &'static str> {
+			Self::set_temp_storage(Files::<T>::iter_keys().count() as u32, "file_storage_v2_files");
+			Ok(())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			let files: u32 =
+				Self::get_temp_storage("file_storage_v2_files").ok_or("no file count")?;
+			frame_support::ensure!(
+				Pallet::<T>::on_chain_storage_version() == 2,
+				"storage version not updated"
+			);
+			// `iter` drops entries that fail to decode, so this also checks every file decodes.
+			frame_support::ensure!(Files::<T>::iter().count() as u32 == files, "files were lost");
+			Ok(())
+		}
+	}
+}
+
+/// Version 3: `FilesOwned` lists every file of its owner.
+pub mod v3 {
+	use crate::pallet::{Config, Files, FilesOwned, Pallet};
+	use core::marker::PhantomData;
+	use frame_support::{
+		sp_std::{collections::btree_map::BTreeMap, vec::Vec},
+		traits::{Get, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+		weights::Weight,
+	};
+	#[cfg(feature = "try-runtime")]
+	use frame_support::traits::OnRuntimeUpgradeHelpersExt;
+
+	const LOG_TARGET: &str = "runtime::file-storage";
+
+	/// Rebuilds `FilesOwned` from `Files`. Only files created by `create_file` since it enforces
+	/// `FileOwnedLimit` were recorded there, so earlier files were missing from their owner's list
+	/// and did not count towards the limit.
+	///
+	/// Files beyond `MaxFileOwned` for an owner cannot be listed and are left out, with a warning.
+	pub struct MigrateToV3<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV3<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 2 {
+				log::info!(target: LOG_TARGET, "MigrateToV3 not applicable, skipping");
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut files = 0u64;
+			let mut by_owner = BTreeMap::<T::AccountId, Vec<T::Hash>>::new();
+			for (cid, file) in Files::<T>::iter() {
+				by_owner.entry(file.owner).or_default().push(cid);
+				files += 1;
+			}
+			let owners = by_owner.len() as u64;
+			for (owner, cids) in by_owner {
+				FilesOwned::<T>::mutate(&owner, |owned| {
+					// Keep the order of the files already listed, and drop any that do not exist.
+					owned.retain(|cid| cids.contains(cid));
+					for cid in cids {
+						if !owned.contains(&cid) && owned.try_push(cid).is_err() {
+							log::warn!(
+								target: LOG_TARGET,
+								"{:?} owns more than MaxFileOwned files, {:?} is not listed",
+								owner,
+								cid
+							);
+						}
+					}
+				});
+			}
+			StorageVersion::new(3).put::<Pallet<T>>();
+
+			log::info!(target: LOG_TARGET, "MigrateToV3 listed the files of {} owners", owners);
+			T::DbWeight::get().reads_writes(files + owners + 1, owners + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<(), &'static str> {
+			Self::set_temp_storage(Files::<T>::iter_keys().count() as u32, "file_storage_v3_files");
+			Ok(())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			let files: u32 =
+				Self::get_temp_storage("file_storage_v3_files").ok_or("no file count")?;
+			frame_support::ensure!(
+				Pallet::<T>::on_chain_storage_version() == 3,
+				"storage version not updated"
+			);
+			frame_support::ensure!(
+				Files::<T>::iter_keys().count() as u32 == files,
+				"files were lost"
+			);
+			for (cid, file) in Files::<T>::iter() {
+				let owned = FilesOwned::<T>::get(&file.owner);
+				frame_support::ensure!(
+					owned.contains(&cid) || owned.len() == T::MaxFileOwned::get() as usize,
+					"file missing from its owner's files"
+				);
+			}
+			for (owner, owned) in FilesOwned::<T>::iter() {
+				for cid in owned.iter() {
+					frame_support::ensure!(
+						Files::<T>::get(cid).map_or(false, |file| file.owner == owner),
+						"FilesOwned lists a file the account does not own"
+					);
+				}
+			}
+			Ok(())
+		}
+	}
+}
@@ -0,0 +1,264 @@
+use crate::{
+	mock::*, CntFileDownloaded, Error, Event as FileStorageEvent, FileCnt, Files, FilesOwned,
+	Purchases, PurgingPurchases,
+};
+use frame_support::{assert_noop, assert_ok, dispatch::DispatchResult, traits::Hooks};
+use sp_core::H256;
+use sp_runtime::traits::BadOrigin;
+
+/// The asset files are priced in, created by [`create_asset`].
+const ASSET: u32 = 7;
+
+fn cid(n: u8) -> H256 {
+	H256::repeat_byte(n)
+}
+
+/// Registers the file `cid(n)` of `owner` at `price`, in `asset_id` if given.
+fn create(owner: u64, n: u8, price: Option<u64>, asset_id: Option<u32>) -> DispatchResult {
+	FileStorage::create_file(
+		Origin::signed(owner),
+		cid(n),
+		price,
+		asset_id,
+		None,
+		b"ipfs://file".to_vec(),
+		true,
+		1024,
+	)
+}
+
+/// Number of recorded purchases of the file `cid(n)`.
+fn purchases(n: u8) -> usize {
+	Purchases::<Test>::iter_prefix(cid(n)).count()
+}
+
+/// Creates [`ASSET`], owned by account 1, and gives `balance` of it to account 2.
+fn create_asset(balance: u64) {
+	assert_ok!(Assets::force_create(Origin::root(), ASSET, 1, true, 1));
+	assert_ok!(Assets::mint(Origin::signed(1), ASSET, 2, balance));
+}
+
+#[test]
+fn create_file_records_the_file() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(create(1, 1, Some(10), None));
+
+		let file = Files::<Test>::get(cid(1)).unwrap();
+		assert_eq!((file.owner, file.price, file.asset_id), (1, Some(10), None));
+		assert_eq!((file.block_number, file.timestamp), (1, NOW));
+		assert_eq!(FilesOwned::<Test>::get(1).into_inner(), vec![cid(1)]);
+		assert_eq!(FileCnt::<Test>::get(), 1);
+		System::assert_last_event(FileStorageEvent::FileCreated { who: 1, cid: cid(1) }.into());
+
+		assert_noop!(create(2, 1, None, None), Error::<Test>::AlreadyUploaded);
+	});
+}
+
+#[test]
+fn create_file_checks_the_asset() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(create(1, 1, Some(10), Some(ASSET)), Error::<Test>::AssetNotExist);
+
+		create_asset(50);
+		assert_noop!(create(1, 1, None, Some(ASSET)), Error::<Test>::AssetWithoutPrice);
+		assert_ok!(create(1, 1, Some(10), Some(ASSET)));
+		assert_eq!(Files::<Test>::get(cid(1)).unwrap().asset_id, Some(ASSET));
+	});
+}
+
+#[test]
+fn purchase_pays_the_owner_in_the_native_currency() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(create(1, 1, Some(10), None));
+
+		assert_ok!(FileStorage::purchase_file(Origin::signed(2), cid(1), 15));
+
+		// The listed price is paid, not the bid.
+		assert_eq!((Balances::free_balance(1), Balances::free_balance(2)), (110, 90));
+		assert!(Purchases::<Test>::contains_key(cid(1), 2));
+		assert!(FileStorage::has_purchased(&cid(1), &2));
+		System::assert_last_event(
+			FileStorageEvent::FilePurchased {
+				buyer: 2,
+				seller: 1,
+				cid: cid(1),
+				asset_id: None,
+				price: 10,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn purchase_pays_the_owner_in_the_asset() {
+	new_test_ext().execute_with(|| {
+		create_asset(50);
+		assert_ok!(create(1, 1, Some(20), Some(ASSET)));
+
+		assert_ok!(FileStorage::purchase_file(Origin::signed(2), cid(1), 20));
+
+		assert_eq!((Assets::balance(ASSET, 1), Assets::balance(ASSET, 2)), (20, 30));
+		// The native balances are untouched.
+		assert_eq!(Balances::free_balance(2), 100);
+		assert!(Purchases::<Test>::contains_key(cid(1), 2));
+		System::assert_last_event(
+			FileStorageEvent::FilePurchased {
+				buyer: 2,
+				seller: 1,
+				cid: cid(1),
+				asset_id: Some(ASSET),
+				price: 20,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn purchase_fails_without_enough_of_the_asset() {
+	new_test_ext().execute_with(|| {
+		create_asset(10);
+		assert_ok!(create(1, 1, Some(20), Some(ASSET)));
+
+		assert!(FileStorage::purchase_file(Origin::signed(2), cid(1), 20).is_err());
+		assert!(!Purchases::<Test>::contains_key(cid(1), 2));
+		assert_eq!(Assets::balance(ASSET, 2), 10);
+	});
+}
+
+#[test]
+fn purchase_checks_the_file_and_the_buyer() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(create(1, 1, Some(10), None));
+		assert_ok!(create(1, 2, None, None));
+
+		assert_noop!(
+			FileStorage::purchase_file(Origin::signed(2), cid(3), 10),
+			Error::<Test>::FileNotExist
+		);
+		assert_noop!(
+			FileStorage::purchase_file(Origin::signed(2), cid(2), 10),
+			Error::<Test>::FileNotForSale
+		);
+		assert_noop!(
+			FileStorage::purchase_file(Origin::signed(1), cid(1), 10),
+			Error::<Test>::BuyerIsFileOwner
+		);
+		assert_noop!(
+			FileStorage::purchase_file(Origin::signed(2), cid(1), 9),
+			Error::<Test>::BidPriceTooLow
+		);
+
+		assert_ok!(FileStorage::purchase_file(Origin::signed(2), cid(1), 10));
+		assert_noop!(
+			FileStorage::purchase_file(Origin::signed(2), cid(1), 10),
+			Error::<Test>::AlreadyPurchased
+		);
+	});
+}
+
+#[test]
+fn force_remove_file_requires_the_force_origin() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(create(1, 1, None, None));
+
+		assert_noop!(FileStorage::force_remove_file(Origin::signed(1), cid(1)), BadOrigin);
+		assert_noop!(
+			FileStorage::force_remove_file(Origin::root(), cid(2)),
+			Error::<Test>::FileNotExist
+		);
+	});
+}
+
+#[test]
+fn force_remove_file_cleans_up_after_the_file() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(create(1, 1, Some(10), None));
+		assert_ok!(create(1, 2, None, None));
+		assert_ok!(FileStorage::purchase_file(Origin::signed(2), cid(1), 10));
+		CntFileDownloaded::<Test>::insert(cid(1), 3);
+
+		assert_ok!(FileStorage::force_remove_file(Origin::root(), cid(1)));
+
+		assert_eq!(Files::<Test>::get(cid(1)), None);
+		assert_eq!(FilesOwned::<Test>::get(1).into_inner(), vec![cid(2)]);
+		assert_eq!(FileCnt::<Test>::get(), 1);
+		assert_eq!(CntFileDownloaded::<Test>::get(cid(1)), 0);
+		assert_eq!(purchases(1), 0);
+		assert_eq!(PurgingPurchases::<Test>::get(cid(1)), None);
+		System::assert_last_event(FileStorageEvent::FileRemoved { owner: 1, cid: cid(1) }.into());
+
+		// With its purchases gone, the cid can be registered again.
+		assert_ok!(create(3, 1, None, None));
+	});
+}
+
+#[test]
+fn purchases_beyond_the_limit_are_cleared_in_later_blocks() {
+	let mut ext = new_test_ext();
+	ext.execute_with(|| {
+		assert_ok!(create(1, 1, Some(10), None));
+		for buyer in 2..=6 {
+			assert_ok!(FileStorage::purchase_file(Origin::signed(buyer), cid(1), 10));
+		}
+	});
+	// Each block starts from committed state, as on chain. Clearing a prefix again within the
+	// same overlay would count the purchases it already cleared.
+	ext.commit_all().unwrap();
+
+	ext.execute_with(|| {
+		assert_ok!(FileStorage::force_remove_file(Origin::root(), cid(1)));
+		assert_eq!(purchases(1), 5 - MAX_PURCHASES_REMOVED as usize);
+		assert_eq!(PurgingPurchases::<Test>::get(cid(1)), Some(1));
+		assert_noop!(create(2, 1, None, None), Error::<Test>::PurchasesBeingCleared);
+
+		// Nothing more is cleared in the block of the take-down.
+		FileStorage::on_idle(1, u64::MAX);
+		assert_eq!(purchases(1), 3);
+	});
+	ext.commit_all().unwrap();
+
+	ext.execute_with(|| {
+		System::set_block_number(2);
+		// Nor in a block without spare weight.
+		assert_eq!(FileStorage::on_idle(2, 0), 0);
+		assert_eq!(purchases(1), 3);
+
+		FileStorage::on_idle(2, u64::MAX);
+		assert_eq!(purchases(1), 3 - MAX_PURCHASES_REMOVED as usize);
+		assert_eq!(PurgingPurchases::<Test>::get(cid(1)), Some(1));
+	});
+	ext.commit_all().unwrap();
+
+	ext.execute_with(|| {
+		System::set_block_number(3);
+		FileStorage::on_idle(3, u64::MAX);
+		assert_eq!(purchases(1), 0);
+		assert_eq!(PurgingPurchases::<Test>::get(cid(1)), None);
+
+		assert_ok!(create(2, 1, None, None));
+	});
+}
+
+#[test]
+fn migration_to_v3_lists_every_file_of_its_owner() {
+	use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		assert_ok!(create(1, 1, None, None));
+		assert_ok!(create(1, 2, None, None));
+		assert_ok!(create(2, 3, None, None));
+		// As if created before `create_file` listed files in `FilesOwned`.
+		FilesOwned::<Test>::mutate(1, |owned| owned.retain(|listed| *listed != cid(1)));
+		FilesOwned::<Test>::remove(2);
+		StorageVersion::new(2).put::<FileStorage>();
+
+		crate::migrations::v3::MigrateToV3::<Test>::on_runtime_upgrade();
+
+		// Files already listed keep their place.
+		assert_eq!(FilesOwned::<Test>::get(1).into_inner(), vec![cid(2), cid(1)]);
+		assert_eq!(FilesOwned::<Test>::get(2).into_inner(), vec![cid(3)]);
+		assert_eq!(FileStorage::on_chain_storage_version(), 3);
+	});
+}
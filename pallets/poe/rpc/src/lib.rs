@@ -0,0 +1,137 @@
+//! RPC interface for the PoE pallet.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::Codec;
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use pallet_poe_runtime_api::PoeApi as PoeRuntimeApi;
+
+/// The largest page `poe_claimsOf` returns.
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+#[rpc(client, server)]
+pub trait PoeApi<BlockHash, AccountId, BlockNumber> {
+	/// Owner, block number and time of creation (milliseconds since the Unix epoch) of `claim`.
+	#[method(name = "poe_claim")]
+	fn claim(
+		&self,
+		claim: BlockHash,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<(AccountId, BlockNumber, u64)>>;
+
+	/// Up to `limit` claims owned by `owner`, continuing after the claim `start_after` if given.
+	///
+	/// Pass the last claim of a page as `start_after` to fetch the next one. `limit` may be at
+	/// most [`MAX_PAGE_SIZE`].
+	#[method(name = "poe_claimsOf")]
+	fn claims_of(
+		&self,
+		owner: AccountId,
+		start_after: Option<BlockHash>,
+		limit: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<BlockHash>>;
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+	/// The requested page size is larger than [`MAX_PAGE_SIZE`].
+	PageTooLarge,
+	/// The runtime at the requested block predates the method.
+	Unsupported,
+}
+
+impl From<Error> for i32 {
+	fn from(e: Error) -> i32 {
+		match e {
+			Error::RuntimeError => 1,
+			Error::PageTooLarge => 2,
+			Error::Unsupported => 3,
+		}
+	}
+}
+
+/// Provides RPC methods to query the PoE pallet.
+pub struct Poe<C, Block> {
+	client: Arc<C>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> Poe<C, Block> {
+	/// Creates a new instance of the Poe RPC helper.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+fn runtime_error(message: &'static str, e: impl std::fmt::Display) -> jsonrpsee::core::Error {
+	CallError::Custom(ErrorObject::owned(Error::RuntimeError.into(), message, Some(e.to_string())))
+		.into()
+}
+
+impl<C, Block, AccountId, BlockNumber>
+	PoeApiServer<<Block as BlockT>::Hash, AccountId, BlockNumber> for Poe<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: PoeRuntimeApi<Block, AccountId, BlockNumber>,
+	AccountId: Codec + Send + Sync + 'static,
+	BlockNumber: Codec + Send + Sync + 'static,
+{
+	fn claim(
+		&self,
+		claim: Block::Hash,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Option<(AccountId, BlockNumber, u64)>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.claim(&at, claim).map_err(|e| runtime_error("Unable to query claim.", e))
+	}
+
+	fn claims_of(
+		&self,
+		owner: AccountId,
+		start_after: Option<Block::Hash>,
+		limit: u32,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<Block::Hash>> {
+		if limit > MAX_PAGE_SIZE {
+			return Err(CallError::Custom(ErrorObject::owned(
+				Error::PageTooLarge.into(),
+				format!("Page size must be at most {}.", MAX_PAGE_SIZE),
+				None::<()>,
+			))
+			.into())
+		}
+
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		// `claims_of` was added in version 2 of the runtime API.
+		let version = api
+			.api_version::<dyn PoeRuntimeApi<Block, AccountId, BlockNumber>>(&at)
+			.map_err(|e| runtime_error("Unable to query the runtime API version.", e))?;
+		if version.map_or(true, |v| v < 2) {
+			return Err(CallError::Custom(ErrorObject::owned(
+				Error::Unsupported.into(),
+				"The runtime at this block does not index claims by owner.",
+				None::<()>,
+			))
+			.into())
+		}
+
+		api.claims_of(&at, owner, start_after, limit)
+			.map_err(|e| runtime_error("Unable to query claims.", e))
+	}
+}
@@ -0,0 +1,30 @@
+//! Runtime API definition for the PoE pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_runtime::traits::Block as BlockT;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	/// Queries against `pallet_poe`.
+	///
+	/// Version 2 added [`PoeApi::claims_of`].
+	#[api_version(2)]
+	pub trait PoeApi<AccountId, BlockNumber> where
+		AccountId: Codec,
+		BlockNumber: Codec,
+	{
+		/// Owner, block number and time of creation (milliseconds since the Unix epoch) of
+		/// `claim`, if it exists.
+		fn claim(claim: <Block as BlockT>::Hash) -> Option<(AccountId, BlockNumber, u64)>;
+
+		/// Up to `limit` claims owned by `owner`, continuing after the claim `start_after` if
+		/// given.
+		fn claims_of(
+			owner: AccountId,
+			start_after: Option<<Block as BlockT>::Hash>,
+			limit: u32,
+		) -> Vec<<Block as BlockT>::Hash>;
+	}
+}
@@ -3,15 +3,25 @@
 // Re-export pallet items so that they can be accessed from the crate namespace.
 pub use pallet::*;
 
+pub mod migrations;
 
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
 
 #[frame_support::pallet]
 pub mod pallet {
-  use frame_support::pallet_prelude::*;
+  use frame_support::{pallet_prelude::*, sp_std::vec::Vec, traits::UnixTime};
   use frame_system::pallet_prelude::*;
 
+  /// The current storage version.
+  const STORAGE_VERSION: StorageVersion = StorageVersion::new(3);
+
   #[pallet::pallet]
   #[pallet::generate_store(pub(super) trait Store)]
+  #[pallet::storage_version(STORAGE_VERSION)]
   pub struct Pallet<T>(_);
 
 /// Configure the pallet by specifying the parameters and types on which it depends.
@@ -19,6 +29,9 @@ pub mod pallet {
 pub trait Config: frame_system::Config {
   /// Because this pallet emits events, it depends on the runtime's definition of an event.
   type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+  /// Source of the wall-clock time recorded with each claim.
+  type TimeProvider: UnixTime;
 }
 
 
@@ -31,6 +44,8 @@ pub enum Event<T: Config> {
   ClaimCreated { who: T::AccountId, claim: T::Hash },
   /// Event emitted when a claim is revoked by the owner.
   ClaimRevoked { who: T::AccountId, claim: T::Hash },
+  /// Event emitted when a claim is transferred by its owner to another account.
+  ClaimTransferred { from: T::AccountId, to: T::AccountId, claim: T::Hash },
 }
 
 
@@ -42,9 +57,50 @@ pub enum Error<T> {
   NoSuchClaim,
   /// The claim is owned by another account, so caller can't revoke it.
   NotClaimOwner,
+  /// A claim cannot be transferred to its current owner.
+  TransferToSelf,
 }
+/// Owner, block number and time of creation (milliseconds since the Unix epoch) of each claim.
+///
+/// Claims created before times were recorded carry a time of 0.
+#[pallet::storage]
+#[pallet::getter(fn claims)]
+pub(super) type Claims<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, (T::AccountId, T::BlockNumber, u64)>;
+
+/// Index of the claims owned by each account, kept in sync with `Claims`.
+#[pallet::storage]
+pub(super) type ClaimsByOwner<T: Config> = StorageDoubleMap<_, Blake2_128Concat, T::AccountId, Blake2_128Concat, T::Hash, ()>;
+
+/// Number of claims, kept in sync with `Claims`.
 #[pallet::storage]
-pub(super) type Claims<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, (T::AccountId, T::BlockNumber)>;
+#[pallet::getter(fn claim_count)]
+pub(super) type ClaimCount<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+/// Claims present from the genesis block, as (claim, owner) pairs.
+#[pallet::genesis_config]
+pub struct GenesisConfig<T: Config> {
+  pub claims: Vec<(T::Hash, T::AccountId)>,
+}
+
+#[cfg(feature = "std")]
+impl<T: Config> Default for GenesisConfig<T> {
+  fn default() -> Self {
+    Self { claims: Vec::new() }
+  }
+}
+
+#[pallet::genesis_build]
+impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+  fn build(&self) {
+    for (claim, owner) in &self.claims {
+      assert!(!Claims::<T>::contains_key(claim), "Duplicate claim in genesis.");
+      // Genesis claims are recorded at block 0 and, like claims older than recorded times, time 0.
+      Claims::<T>::insert(claim, (owner.clone(), T::BlockNumber::default(), 0u64));
+      ClaimsByOwner::<T>::insert(owner, claim, ());
+    }
+    ClaimCount::<T>::put(self.claims.len() as u64);
+  }
+}
 
 // Dispatchable functions allow users to interact with the pallet and invoke state changes.
 // These functions materialize as "extrinsics", which are often compared to transactions.
@@ -63,8 +119,13 @@ impl<T: Config> Pallet<T> {
     // Get the block number from the FRAME System pallet.
     let current_block = <frame_system::Pallet<T>>::block_number();
 
-    // Store the claim with the sender and block number.
-    Claims::<T>::insert(&claim, (&sender, current_block));
+    // Get the wall-clock time of the current block.
+    let now = T::TimeProvider::now().as_millis() as u64;
+
+    // Store the claim with the sender, block number and time.
+    Claims::<T>::insert(&claim, (&sender, current_block, now));
+    ClaimsByOwner::<T>::insert(&sender, &claim, ());
+    ClaimCount::<T>::mutate(|count| *count = count.saturating_add(1));
 
     // Emit an event that the claim was created.
     Self::deposit_event(Event::ClaimCreated { who: sender, claim });
@@ -79,17 +140,57 @@ impl<T: Config> Pallet<T> {
     let sender = ensure_signed(origin)?;
 
     // Get owner of the claim, if none return an error.
-    let (owner, _) = Claims::<T>::get(&claim).ok_or(Error::<T>::NoSuchClaim)?;
+    let (owner, _, _) = Claims::<T>::get(&claim).ok_or(Error::<T>::NoSuchClaim)?;
 
     // Verify that sender of the current call is the claim owner.
     ensure!(sender == owner, Error::<T>::NotClaimOwner);
 
     // Remove claim from storage.
     Claims::<T>::remove(&claim);
+    ClaimsByOwner::<T>::remove(&sender, &claim);
+    ClaimCount::<T>::mutate(|count| *count = count.saturating_sub(1));
 
     // Emit an event that the claim was erased.
     Self::deposit_event(Event::ClaimRevoked { who: sender, claim });
     Ok(())
   }
+
+  #[pallet::weight(0)]
+  pub fn transfer_claim(origin: OriginFor<T>, claim: T::Hash, dest: T::AccountId) -> DispatchResult {
+    let sender = ensure_signed(origin)?;
+
+    // Get the claim, if none return an error.
+    let (owner, block, time) = Claims::<T>::get(&claim).ok_or(Error::<T>::NoSuchClaim)?;
+
+    // Verify that sender of the current call is the claim owner.
+    ensure!(sender == owner, Error::<T>::NotClaimOwner);
+    ensure!(sender != dest, Error::<T>::TransferToSelf);
+
+    // Hand the claim over, keeping its creation block and time.
+    Claims::<T>::insert(&claim, (&dest, block, time));
+    ClaimsByOwner::<T>::remove(&sender, &claim);
+    ClaimsByOwner::<T>::insert(&dest, &claim, ());
+
+    Self::deposit_event(Event::ClaimTransferred { from: sender, to: dest, claim });
+    Ok(())
+  }
+}
+
+impl<T: Config> Pallet<T> {
+  /// Up to `limit` claims owned by `owner`, continuing after `start_after` if given.
+  ///
+  /// Claims are returned in storage order, which is stable but not sorted by creation, so pages
+  /// are fetched by passing the last claim of a page as `start_after` of the next one.
+  pub fn claims_of(owner: &T::AccountId, start_after: Option<T::Hash>, limit: u32) -> Vec<T::Hash> {
+    match start_after {
+      Some(last) => ClaimsByOwner::<T>::iter_key_prefix_from(
+        owner,
+        ClaimsByOwner::<T>::hashed_key_for(owner, last),
+      )
+      .take(limit as usize)
+      .collect(),
+      None => ClaimsByOwner::<T>::iter_key_prefix(owner).take(limit as usize).collect(),
+    }
+  }
+}
 }
-}
\ No newline at end of file
@@ -0,0 +1,164 @@
+//! Storage migrations for the PoE pallet.
+
+/// Version 1: claims record their creation time next to the block number.
+pub mod v1 {
+	use crate::pallet::{Claims, Config, Pallet};
+	use core::marker::PhantomData;
+	use frame_support::{
+		traits::{Get, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+		weights::Weight,
+	};
+	#[cfg(feature = "try-runtime")]
+	use frame_support::traits::OnRuntimeUpgradeHelpersExt;
+
+	const LOG_TARGET: &str = "runtime::poe";
+
+	/// Extends every `Claims` entry with a creation time of 0, since the real time of existing
+	/// claims is unknown.
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() >= 1 {
+				log::info!(target: LOG_TARGET, "MigrateToV1 already applied, skipping");
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut translated = 0u64;
+			Claims::<T>::translate::<(T::AccountId, T::BlockNumber), _>(|_, (owner, block)| {
+				translated += 1;
+				Some((owner, block, 0))
+			});
+			StorageVersion::new(1).put::<Pallet<T>>();
+
+			log::info!(target: LOG_TARGET, "MigrateToV1 translated {} claims", translated);
+			T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<(), &'static str> {
+			Self::set_temp_storage(Claims::<T>::iter_keys().count() as u32, "poe_v1_claims");
+			Ok(())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			let claims: u32 = Self::get_temp_storage("poe_v1_claims").ok_or("no claim count")?;
+			frame_support::ensure!(
+				Pallet::<T>::on_chain_storage_version() >= 1,
+				"storage version not updated"
+			);
+			frame_support::ensure!(
+				Claims::<T>::iter_keys().count() as u32 == claims,
+				"claims were lost"
+			);
+			Ok(())
+		}
+	}
+}
+
+/// Version 2: claims are indexed by owner in `ClaimsByOwner`.
+pub mod v2 {
+	use crate::pallet::{Claims, ClaimsByOwner, Config, Pallet};
+	use core::marker::PhantomData;
+	use frame_support::{
+		traits::{Get, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+		weights::Weight,
+	};
+	#[cfg(feature = "try-runtime")]
+	use frame_support::traits::OnRuntimeUpgradeHelpersExt;
+
+	const LOG_TARGET: &str = "runtime::poe";
+
+	/// Builds the `ClaimsByOwner` index from the existing `Claims`.
+	pub struct MigrateToV2<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV2<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 1 {
+				log::info!(target: LOG_TARGET, "MigrateToV2 not applicable, skipping");
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut indexed = 0u64;
+			for (claim, (owner, _, _)) in Claims::<T>::iter() {
+				ClaimsByOwner::<T>::insert(owner, claim, ());
+				indexed += 1;
+			}
+			StorageVersion::new(2).put::<Pallet<T>>();
+
+			log::info!(target: LOG_TARGET, "MigrateToV2 indexed {} claims", indexed);
+			T::DbWeight::get().reads_writes(indexed + 1, indexed + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<(), &'static str> {
+			Self::set_temp_storage(Claims::<T>::iter_keys().count() as u32, "poe_v2_claims");
+			Ok(())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			let claims: u32 = Self::get_temp_storage("poe_v2_claims").ok_or("no claim count")?;
+			frame_support::ensure!(
+				Pallet::<T>::on_chain_storage_version() == 2,
+				"storage version not updated"
+			);
+			frame_support::ensure!(
+				ClaimsByOwner::<T>::iter_keys().count() as u32 == claims,
+				"index does not match claims"
+			);
+			for (claim, (owner, _, _)) in Claims::<T>::iter() {
+				frame_support::ensure!(
+					ClaimsByOwner::<T>::contains_key(owner, claim),
+					"claim missing from index"
+				);
+			}
+			Ok(())
+		}
+	}
+}
+
+/// Version 3: the number of claims is kept in `ClaimCount`.
+pub mod v3 {
+	use crate::pallet::{ClaimCount, Claims, Config, Pallet};
+	use core::marker::PhantomData;
+	use frame_support::{
+		traits::{Get, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+		weights::Weight,
+	};
+
+	const LOG_TARGET: &str = "runtime::poe";
+
+	/// Seeds `ClaimCount` with the number of existing `Claims`.
+	pub struct MigrateToV3<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV3<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 2 {
+				log::info!(target: LOG_TARGET, "MigrateToV3 not applicable, skipping");
+				return T::DbWeight::get().reads(1)
+			}
+
+			let claims = Claims::<T>::iter_keys().count() as u64;
+			ClaimCount::<T>::put(claims);
+			StorageVersion::new(3).put::<Pallet<T>>();
+
+			log::info!(target: LOG_TARGET, "MigrateToV3 counted {} claims", claims);
+			T::DbWeight::get().reads_writes(claims + 1, 2)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			frame_support::ensure!(
+				Pallet::<T>::on_chain_storage_version() == 3,
+				"storage version not updated"
+			);
+			frame_support::ensure!(
+				ClaimCount::<T>::get() == Claims::<T>::iter_keys().count() as u64,
+				"ClaimCount does not match claims"
+			);
+			Ok(())
+		}
+	}
+}
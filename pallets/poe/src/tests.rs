@@ -0,0 +1,135 @@
+use crate::{mock::*, ClaimCount, Claims, ClaimsByOwner, Error, Event as PoeEvent};
+use frame_support::{assert_noop, assert_ok};
+use sp_core::H256;
+
+fn claim(n: u8) -> H256 {
+	H256::repeat_byte(n)
+}
+
+/// The claims indexed for `owner`, sorted so that tests do not depend on storage order.
+fn indexed(owner: u64) -> Vec<H256> {
+	let mut claims: Vec<_> = ClaimsByOwner::<Test>::iter_key_prefix(owner).collect();
+	claims.sort();
+	claims
+}
+
+#[test]
+fn create_claim_records_owner_block_and_time() {
+	new_test_ext().execute_with(|| {
+		// Events are not emitted in the genesis block.
+		System::set_block_number(3);
+
+		assert_ok!(PoeModule::create_claim(Origin::signed(1), claim(1)));
+		assert_eq!(Claims::<Test>::get(claim(1)), Some((1, 3, NOW)));
+		assert_eq!(indexed(1), vec![claim(1)]);
+		assert_eq!(ClaimCount::<Test>::get(), 1);
+		System::assert_last_event(PoeEvent::ClaimCreated { who: 1, claim: claim(1) }.into());
+
+		assert_noop!(
+			PoeModule::create_claim(Origin::signed(2), claim(1)),
+			Error::<Test>::AlreadyClaimed
+		);
+	});
+}
+
+#[test]
+fn revoke_claim_removes_it_from_the_index() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PoeModule::create_claim(Origin::signed(1), claim(1)));
+		assert_ok!(PoeModule::create_claim(Origin::signed(1), claim(2)));
+
+		assert_noop!(
+			PoeModule::revoke_claim(Origin::signed(2), claim(1)),
+			Error::<Test>::NotClaimOwner
+		);
+		assert_ok!(PoeModule::revoke_claim(Origin::signed(1), claim(1)));
+		assert_eq!(Claims::<Test>::get(claim(1)), None);
+		assert_eq!(indexed(1), vec![claim(2)]);
+		assert_eq!(ClaimCount::<Test>::get(), 1);
+
+		assert_noop!(
+			PoeModule::revoke_claim(Origin::signed(1), claim(1)),
+			Error::<Test>::NoSuchClaim
+		);
+	});
+}
+
+#[test]
+fn transfer_claim_moves_it_between_owners() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(3);
+		assert_ok!(PoeModule::create_claim(Origin::signed(1), claim(1)));
+		System::set_block_number(5);
+
+		assert_noop!(
+			PoeModule::transfer_claim(Origin::signed(2), claim(1), 3),
+			Error::<Test>::NotClaimOwner
+		);
+		assert_noop!(
+			PoeModule::transfer_claim(Origin::signed(1), claim(1), 1),
+			Error::<Test>::TransferToSelf
+		);
+		assert_ok!(PoeModule::transfer_claim(Origin::signed(1), claim(1), 2));
+
+		// The claim keeps its creation block and time.
+		assert_eq!(Claims::<Test>::get(claim(1)), Some((2, 3, NOW)));
+		assert_eq!(indexed(1), vec![]);
+		assert_eq!(indexed(2), vec![claim(1)]);
+		System::assert_last_event(
+			PoeEvent::ClaimTransferred { from: 1, to: 2, claim: claim(1) }.into(),
+		);
+
+		// Only the new owner can revoke it, which clears its index.
+		assert_noop!(
+			PoeModule::revoke_claim(Origin::signed(1), claim(1)),
+			Error::<Test>::NotClaimOwner
+		);
+		assert_ok!(PoeModule::revoke_claim(Origin::signed(2), claim(1)));
+		assert_eq!(indexed(2), vec![]);
+	});
+}
+
+#[test]
+fn claims_of_pages_through_the_claims_of_an_owner() {
+	new_test_ext().execute_with(|| {
+		for n in 1..=5 {
+			assert_ok!(PoeModule::create_claim(Origin::signed(1), claim(n)));
+		}
+		assert_ok!(PoeModule::create_claim(Origin::signed(2), claim(6)));
+
+		let first = PoeModule::claims_of(&1, None, 2);
+		assert_eq!(first.len(), 2);
+		let second = PoeModule::claims_of(&1, first.last().copied(), 2);
+		assert_eq!(second.len(), 2);
+		let third = PoeModule::claims_of(&1, second.last().copied(), 2);
+		assert_eq!(third.len(), 1);
+		assert_eq!(PoeModule::claims_of(&1, third.last().copied(), 2), vec![]);
+
+		// The pages cover every claim of the owner exactly once, and none of other owners.
+		let mut all: Vec<_> = first.into_iter().chain(second).chain(third).collect();
+		all.sort();
+		assert_eq!(all, (1..=5).map(claim).collect::<Vec<_>>());
+
+		assert_eq!(PoeModule::claims_of(&2, None, 10), vec![claim(6)]);
+		assert_eq!(PoeModule::claims_of(&3, None, 10), vec![]);
+		assert_eq!(PoeModule::claims_of(&1, None, 0), vec![]);
+	});
+}
+
+#[test]
+fn migration_to_v3_counts_the_claims() {
+	use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		for n in 1..=3 {
+			assert_ok!(PoeModule::create_claim(Origin::signed(1), claim(n)));
+		}
+		ClaimCount::<Test>::kill();
+		StorageVersion::new(2).put::<PoeModule>();
+
+		crate::migrations::v3::MigrateToV3::<Test>::on_runtime_upgrade();
+
+		assert_eq!(ClaimCount::<Test>::get(), 3);
+		assert_eq!(PoeModule::on_chain_storage_version(), 3);
+	});
+}
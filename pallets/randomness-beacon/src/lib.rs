@@ -0,0 +1,363 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Commit-reveal randomness beacon run by the validators.
+//!
+//! A validator first commits to a secret seed by submitting `hash((validator, seed))` with
+//! [`Pallet::commit`], reserving `CommitDeposit`. At least `RevealDelay` blocks later, and no
+//! more than `RevealWindow` blocks after that, it reveals the seed with [`Pallet::reveal`], which
+//! mixes the seed into the accumulated entropy and returns the deposit. A commitment that is not
+//! revealed in time is dropped and its deposit slashed, so validators cannot withhold an
+//! unfavourable seed for free.
+//!
+//! The accumulated entropy is exposed through the [`Randomness`] trait. Until the first reveal it
+//! is the zero hash, known to everyone, which `random` reports by returning block 0.
+//!
+//! Validators take part through the pallet's offchain worker. On a validator node with a
+//! [`KEY_TYPE`] key of a validator account in its keystore, it commits to a fresh seed whenever
+//! that validator has no open commitment, keeps the seed in offchain storage and reveals it once
+//! `RevealDelay` has passed.
+
+// Re-export pallet items so that they can be accessed from the crate namespace.
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+use sp_core::crypto::KeyTypeId;
+
+/// Key type of the keys the offchain worker signs commitments and reveals with.
+///
+/// A validator inserts the key of its account under this type, e.g. with `key insert`.
+pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"rbcn");
+
+/// The keys the offchain worker signs with, sr25519 keys of the validator accounts.
+pub mod crypto {
+	use super::KEY_TYPE;
+	use sp_runtime::{
+		app_crypto::{app_crypto, sr25519},
+		traits::Verify,
+		MultiSignature, MultiSigner,
+	};
+
+	app_crypto!(sr25519, KEY_TYPE);
+
+	/// Signs the transactions of the offchain worker.
+	pub struct AuthorityId;
+
+	impl frame_system::offchain::AppCrypto<MultiSigner, MultiSignature> for AuthorityId {
+		type RuntimeAppPublic = Public;
+		type GenericSignature = sp_core::sr25519::Signature;
+		type GenericPublic = sp_core::sr25519::Public;
+	}
+
+	// For runtimes whose accounts are sr25519 keys, e.g. test runtimes.
+	impl
+		frame_system::offchain::AppCrypto<
+			<sp_core::sr25519::Signature as Verify>::Signer,
+			sp_core::sr25519::Signature,
+		> for AuthorityId
+	{
+		type RuntimeAppPublic = Public;
+		type GenericSignature = sp_core::sr25519::Signature;
+		type GenericPublic = sp_core::sr25519::Public;
+	}
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{Contains, Currency, Imbalance, OnUnbalanced, Randomness, ReservableCurrency},
+	};
+	use frame_system::{
+		offchain::{AppCrypto, CreateSignedTransaction, SendSignedTransaction, Signer},
+		pallet_prelude::*,
+	};
+	use sp_runtime::{
+		offchain::storage::StorageValueRef,
+		traits::{Hash, IdentifyAccount, Saturating},
+		RuntimeAppPublic,
+	};
+	use sp_std::{vec, vec::Vec};
+
+	const LOG_TARGET: &str = "runtime::randomness-beacon";
+
+	/// Prefix of the offchain storage keys of the seeds behind the commitments, by commitment.
+	const SEED_PREFIX: &[u8] = b"randomness-beacon::seed::";
+
+	/// Prefix of the offchain storage keys of the blocks transactions were last sent in.
+	const SENT_PREFIX: &[u8] = b"randomness-beacon::sent::";
+
+	/// Number of blocks the offchain worker waits for a transaction before sending it again.
+	const RESEND_AFTER: u32 = 5;
+
+	pub type BalanceOf<T> =
+		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+	pub type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<
+		<T as frame_system::Config>::AccountId,
+	>>::NegativeImbalance;
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Configure the pallet by specifying the parameters and types on which it depends.
+	#[pallet::config]
+	pub trait Config: CreateSignedTransaction<Call<Self>> + frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// Currency in which commitment deposits are reserved.
+		type Currency: ReservableCurrency<Self::AccountId>;
+
+		/// Accounts allowed to take part in the beacon.
+		type Validators: Contains<Self::AccountId>;
+
+		/// Deposit reserved with every commitment and slashed if it is not revealed in time.
+		#[pallet::constant]
+		type CommitDeposit: Get<BalanceOf<Self>>;
+
+		/// Number of blocks after a commitment before its seed may be revealed.
+		#[pallet::constant]
+		type RevealDelay: Get<Self::BlockNumber>;
+
+		/// Number of blocks, after `RevealDelay`, in which the seed may be revealed.
+		#[pallet::constant]
+		type RevealWindow: Get<Self::BlockNumber>;
+
+		/// The maximum number of commitments made in the same block.
+		#[pallet::constant]
+		type MaxCommitmentsPerBlock: Get<u32>;
+
+		/// Handler for the deposits of unrevealed commitments.
+		type Slash: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+		/// The keys the offchain worker signs commitments and reveals with.
+		type AuthorityId: AppCrypto<Self::Public, Self::Signature>;
+	}
+
+	/// Open commitment of each validator and the block it was made in.
+	#[pallet::storage]
+	#[pallet::getter(fn commitments)]
+	pub type Commitments<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (T::Hash, T::BlockNumber)>;
+
+	/// Validators whose commitment expires at the end of a block, keyed by that block.
+	#[pallet::storage]
+	pub(super) type Deadlines<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		T::BlockNumber,
+		BoundedVec<T::AccountId, T::MaxCommitmentsPerBlock>,
+		ValueQuery,
+	>;
+
+	/// Entropy accumulated from all revealed seeds.
+	#[pallet::storage]
+	#[pallet::getter(fn entropy)]
+	pub type Entropy<T: Config> = StorageValue<_, T::Hash, ValueQuery>;
+
+	/// Block of the last reveal, from which on `Entropy` is known.
+	#[pallet::storage]
+	pub type LastReveal<T: Config> = StorageValue<_, T::BlockNumber, ValueQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A validator committed to a seed.
+		Committed { who: T::AccountId, commitment: T::Hash },
+		/// A validator revealed its seed, which was mixed into the entropy.
+		Revealed { who: T::AccountId, seed: T::Hash },
+		/// A commitment was not revealed in time and its deposit was slashed.
+		CommitmentSlashed { who: T::AccountId, amount: BalanceOf<T> },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// Only validators can take part in the beacon.
+		NotValidator,
+		/// The validator already has an open commitment.
+		AlreadyCommitted,
+		/// Too many commitments were made in this block.
+		TooManyCommitments,
+		/// The validator has no open commitment.
+		NoCommitment,
+		/// The seed cannot be revealed before `RevealDelay` blocks have passed.
+		RevealTooEarly,
+		/// The seed does not match the commitment.
+		InvalidReveal,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_finalize(n: T::BlockNumber) {
+			for who in Deadlines::<T>::take(n) {
+				// The validator may have revealed and committed again since.
+				let expired = matches!(
+					Commitments::<T>::get(&who),
+					Some((_, committed_at)) if Self::deadline(committed_at) == n
+				);
+				if expired {
+					Commitments::<T>::remove(&who);
+					let (imbalance, _) = T::Currency::slash_reserved(&who, T::CommitDeposit::get());
+					let amount = imbalance.peek();
+					T::Slash::on_unbalanced(imbalance);
+					Self::deposit_event(Event::CommitmentSlashed { who, amount });
+				}
+			}
+		}
+
+		/// Commit and reveal for every validator whose key is in the node's keystore.
+		fn offchain_worker(now: T::BlockNumber) {
+			if !sp_io::offchain::is_validator() {
+				return
+			}
+
+			for key in <T::AuthorityId as AppCrypto<_, _>>::RuntimeAppPublic::all() {
+				let generic: <T::AuthorityId as AppCrypto<_, _>>::GenericPublic = key.into();
+				let public: T::Public = generic.into();
+				let who = public.clone().into_account();
+				if !T::Validators::contains(&who) {
+					continue
+				}
+
+				let call = match Commitments::<T>::get(&who) {
+					Some((commitment, committed_at)) => {
+						if now < committed_at.saturating_add(T::RevealDelay::get()) {
+							continue
+						}
+						match Self::stored_seed(&commitment) {
+							Some(seed) if Self::should_send(&commitment, now) =>
+								Call::reveal { seed },
+							// A seed this node did not keep cannot be revealed, and the
+							// commitment is slashed once it expires.
+							_ => continue,
+						}
+					},
+					None => {
+						if !Self::should_send(&who, now) {
+							continue
+						}
+						let seed = T::Hashing::hash(&sp_io::offchain::random_seed());
+						let commitment = Self::commitment_of(&who, &seed);
+						StorageValueRef::persistent(&Self::offchain_key(SEED_PREFIX, &commitment))
+							.set(&seed);
+						Call::commit { commitment }
+					},
+				};
+
+				let signer = Signer::<T, T::AuthorityId>::all_accounts().with_filter(vec![public]);
+				for (_, result) in signer.send_signed_transaction(|_| call.clone()) {
+					if result.is_err() {
+						log::warn!(target: LOG_TARGET, "failed to submit {:?}", call);
+					}
+				}
+			}
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Commit to a seed, where `commitment` is the hash of `(validator, seed)`.
+		///
+		/// Reserves `CommitDeposit` until the seed is revealed.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 3))]
+		pub fn commit(origin: OriginFor<T>, commitment: T::Hash) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(T::Validators::contains(&who), Error::<T>::NotValidator);
+			ensure!(!Commitments::<T>::contains_key(&who), Error::<T>::AlreadyCommitted);
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			Deadlines::<T>::try_mutate(Self::deadline(now), |expiring| {
+				expiring.try_push(who.clone()).map_err(|_| Error::<T>::TooManyCommitments)
+			})?;
+			T::Currency::reserve(&who, T::CommitDeposit::get())?;
+			Commitments::<T>::insert(&who, (commitment, now));
+
+			Self::deposit_event(Event::Committed { who, commitment });
+			Ok(())
+		}
+
+		/// Reveal the seed of the open commitment and get the deposit back.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 3))]
+		pub fn reveal(origin: OriginFor<T>, seed: T::Hash) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let (commitment, committed_at) =
+				Commitments::<T>::get(&who).ok_or(Error::<T>::NoCommitment)?;
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(
+				now >= committed_at.saturating_add(T::RevealDelay::get()),
+				Error::<T>::RevealTooEarly
+			);
+			ensure!(
+				T::Hashing::hash_of(&(&who, &seed)) == commitment,
+				Error::<T>::InvalidReveal
+			);
+
+			Commitments::<T>::remove(&who);
+			T::Currency::unreserve(&who, T::CommitDeposit::get());
+			Entropy::<T>::mutate(|entropy| *entropy = T::Hashing::hash_of(&(*entropy, seed)));
+			LastReveal::<T>::put(now);
+
+			Self::deposit_event(Event::Revealed { who, seed });
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Randomness<T::Hash, T::BlockNumber> for Pallet<T> {
+		/// Entropy mixed with `subject`, and the block from which on it is known.
+		///
+		/// That block is 0 until the first reveal, so the value must not be relied upon for
+		/// anything committed to since the genesis block.
+		fn random(subject: &[u8]) -> (T::Hash, T::BlockNumber) {
+			let mut input: Vec<u8> = subject.to_vec();
+			input.extend_from_slice(Entropy::<T>::get().as_ref());
+
+			(T::Hashing::hash(&input[..]), LastReveal::<T>::get())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The last block in which a commitment made in `committed_at` can be revealed.
+		fn deadline(committed_at: T::BlockNumber) -> T::BlockNumber {
+			committed_at
+				.saturating_add(T::RevealDelay::get())
+				.saturating_add(T::RevealWindow::get())
+		}
+
+		/// The commitment a validator must submit for `seed`.
+		pub fn commitment_of(who: &T::AccountId, seed: &T::Hash) -> T::Hash {
+			T::Hashing::hash_of(&(who, seed))
+		}
+
+		/// Offchain storage key of `id` under `prefix`.
+		fn offchain_key(prefix: &[u8], id: &impl Encode) -> Vec<u8> {
+			let mut key = prefix.to_vec();
+			id.encode_to(&mut key);
+			key
+		}
+
+		/// The seed this node committed to with `commitment`, if any.
+		fn stored_seed(commitment: &T::Hash) -> Option<T::Hash> {
+			StorageValueRef::persistent(&Self::offchain_key(SEED_PREFIX, commitment))
+				.get::<T::Hash>()
+				.ok()
+				.flatten()
+		}
+
+		/// Whether the transaction identified by `id` was not sent in the last `RESEND_AFTER`
+		/// blocks, recording that it is sent at `now` if so.
+		fn should_send(id: &impl Encode, now: T::BlockNumber) -> bool {
+			let sent = StorageValueRef::persistent(&Self::offchain_key(SENT_PREFIX, id));
+			let result = sent.mutate(|last: Result<Option<T::BlockNumber>, _>| match last {
+				Ok(Some(last)) if now < last.saturating_add(RESEND_AFTER.into()) => Err(()),
+				_ => Ok(now),
+			});
+			result.is_ok()
+		}
+	}
+}
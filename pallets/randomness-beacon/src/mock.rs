@@ -0,0 +1,165 @@
+use crate as pallet_randomness_beacon;
+use frame_support::{
+	parameter_types,
+	traits::{ConstU16, ConstU32, ConstU64, Contains},
+};
+use frame_system as system;
+use sp_core::{sr25519, H256};
+use sp_runtime::{
+	testing::{Header, TestXt},
+	traits::{BlakeTwo256, Extrinsic as ExtrinsicT, IdentifyAccount, IdentityLookup, Verify},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+/// Accounts are sr25519 keys, as the offchain worker signs with those.
+pub type AccountId = <<sr25519::Signature as Verify>::Signer as IdentifyAccount>::AccountId;
+
+/// The extrinsics the offchain worker submits.
+pub type Extrinsic = TestXt<Call, ()>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		RandomnessBeacon: pallet_randomness_beacon::{Pallet, Call, Storage, Event<T>},
+	}
+);
+
+impl system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type Balance = u64;
+	type Event = Event;
+	type DustRemoval = ();
+	type ExistentialDeposit = ConstU64<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+impl frame_system::offchain::SigningTypes for Test {
+	type Public = <sr25519::Signature as Verify>::Signer;
+	type Signature = sr25519::Signature;
+}
+
+impl<LocalCall> frame_system::offchain::SendTransactionTypes<LocalCall> for Test
+where
+	Call: From<LocalCall>,
+{
+	type OverarchingCall = Call;
+	type Extrinsic = Extrinsic;
+}
+
+impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Test
+where
+	Call: From<LocalCall>,
+{
+	fn create_transaction<C: frame_system::offchain::AppCrypto<Self::Public, Self::Signature>>(
+		call: Call,
+		_public: <sr25519::Signature as Verify>::Signer,
+		_account: AccountId,
+		nonce: u64,
+	) -> Option<(Call, <Extrinsic as ExtrinsicT>::SignaturePayload)> {
+		Some((call, (nonce, ())))
+	}
+}
+
+/// An account that is not a validator; every other account is one.
+pub fn outsider() -> AccountId {
+	sr25519::Public::from_raw([99; 32])
+}
+
+pub fn account(n: u8) -> AccountId {
+	sr25519::Public::from_raw([n; 32])
+}
+
+pub struct AllButOutsider;
+
+impl Contains<AccountId> for AllButOutsider {
+	fn contains(who: &AccountId) -> bool {
+		*who != outsider()
+	}
+}
+
+/// Deposit reserved by every commitment.
+pub const DEPOSIT: u64 = 10;
+/// Blocks after a commitment before it can be revealed.
+pub const DELAY: u64 = 2;
+/// Blocks after `DELAY` in which it can be revealed.
+pub const WINDOW: u64 = 5;
+
+parameter_types! {
+	pub const CommitDeposit: u64 = DEPOSIT;
+	pub const RevealDelay: u64 = DELAY;
+	pub const RevealWindow: u64 = WINDOW;
+}
+
+impl pallet_randomness_beacon::Config for Test {
+	type Event = Event;
+	type Currency = Balances;
+	type Validators = AllButOutsider;
+	type CommitDeposit = CommitDeposit;
+	type RevealDelay = RevealDelay;
+	type RevealWindow = RevealWindow;
+	type MaxCommitmentsPerBlock = ConstU32<2>;
+	type Slash = ();
+	type AuthorityId = pallet_randomness_beacon::crypto::AuthorityId;
+}
+
+// Build genesis storage according to the mock runtime, with 100 units for accounts 1 to 3 and
+// the outsider.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: vec![(account(1), 100), (account(2), 100), (account(3), 100), (outsider(), 100)],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
+
+/// Run blocks up to and including `n`, finalizing each one.
+pub fn run_to_block(n: u64) {
+	use frame_support::traits::Hooks;
+
+	while System::block_number() < n {
+		RandomnessBeacon::on_finalize(System::block_number());
+		System::set_block_number(System::block_number() + 1);
+	}
+}
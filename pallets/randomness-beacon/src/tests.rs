@@ -0,0 +1,200 @@
+use crate::{mock::*, Commitments, Error, Event as BeaconEvent, KEY_TYPE};
+use codec::Decode;
+use frame_support::{
+	assert_noop, assert_ok,
+	traits::{Currency, Hooks, Randomness},
+};
+use sp_core::{
+	offchain::{testing, OffchainDbExt, OffchainWorkerExt, TransactionPoolExt},
+	H256,
+};
+use sp_keystore::{testing::KeyStore, KeystoreExt, SyncCryptoStore};
+use sp_runtime::traits::{BlakeTwo256, Hash};
+use std::sync::Arc;
+
+fn seed(n: u8) -> H256 {
+	H256::repeat_byte(n)
+}
+
+fn commit(who: AccountId, seed: H256) -> frame_support::dispatch::DispatchResult {
+	RandomnessBeacon::commit(Origin::signed(who), RandomnessBeacon::commitment_of(&who, &seed))
+}
+
+#[test]
+fn commit_reserves_the_deposit() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(commit(account(1), seed(1)));
+
+		let commitment = RandomnessBeacon::commitment_of(&account(1), &seed(1));
+		assert_eq!(Commitments::<Test>::get(account(1)), Some((commitment, 1)));
+		assert_eq!(Balances::reserved_balance(account(1)), DEPOSIT);
+		System::assert_last_event(BeaconEvent::Committed { who: account(1), commitment }.into());
+	});
+}
+
+#[test]
+fn commit_checks_the_committer() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(commit(outsider(), seed(1)), Error::<Test>::NotValidator);
+
+		assert_ok!(commit(account(1), seed(1)));
+		assert_noop!(commit(account(1), seed(2)), Error::<Test>::AlreadyCommitted);
+
+		assert_ok!(commit(account(2), seed(2)));
+		assert_noop!(commit(account(3), seed(3)), Error::<Test>::TooManyCommitments);
+	});
+}
+
+#[test]
+fn reveal_mixes_the_seed_into_the_entropy() {
+	new_test_ext().execute_with(|| {
+		// Nothing is known before the first reveal.
+		assert_eq!(RandomnessBeacon::entropy(), H256::zero());
+		assert_eq!(RandomnessBeacon::random(b"subject").1, 0);
+
+		assert_ok!(commit(account(1), seed(1)));
+		run_to_block(1 + DELAY);
+		assert_ok!(RandomnessBeacon::reveal(Origin::signed(account(1)), seed(1)));
+
+		assert_eq!(Commitments::<Test>::get(account(1)), None);
+		assert_eq!(Balances::reserved_balance(account(1)), 0);
+		assert_eq!(RandomnessBeacon::entropy(), BlakeTwo256::hash_of(&(H256::zero(), seed(1))));
+		System::assert_last_event(BeaconEvent::Revealed { who: account(1), seed: seed(1) }.into());
+
+		let (first, known_since) = RandomnessBeacon::random(b"subject");
+		assert_eq!(known_since, 1 + DELAY);
+		assert_ne!(first, RandomnessBeacon::random(b"other subject").0);
+	});
+}
+
+#[test]
+fn reveal_checks_timing_and_seed() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			RandomnessBeacon::reveal(Origin::signed(account(1)), seed(1)),
+			Error::<Test>::NoCommitment
+		);
+
+		assert_ok!(commit(account(1), seed(1)));
+		run_to_block(DELAY);
+		assert_noop!(
+			RandomnessBeacon::reveal(Origin::signed(account(1)), seed(1)),
+			Error::<Test>::RevealTooEarly
+		);
+
+		run_to_block(1 + DELAY);
+		assert_noop!(
+			RandomnessBeacon::reveal(Origin::signed(account(1)), seed(2)),
+			Error::<Test>::InvalidReveal
+		);
+		// Another validator cannot reveal someone else's seed.
+		assert_noop!(
+			RandomnessBeacon::reveal(Origin::signed(account(2)), seed(1)),
+			Error::<Test>::NoCommitment
+		);
+	});
+}
+
+#[test]
+fn unrevealed_commitment_is_slashed_after_the_window() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(commit(account(1), seed(1)));
+
+		// Still open in the last block of the window.
+		run_to_block(1 + DELAY + WINDOW);
+		assert!(Commitments::<Test>::contains_key(account(1)));
+
+		run_to_block(2 + DELAY + WINDOW);
+		assert_eq!(Commitments::<Test>::get(account(1)), None);
+		assert_eq!(Balances::reserved_balance(account(1)), 0);
+		assert_eq!(Balances::free_balance(account(1)), 100 - DEPOSIT);
+		System::assert_last_event(
+			BeaconEvent::CommitmentSlashed { who: account(1), amount: DEPOSIT }.into(),
+		);
+		assert_eq!(RandomnessBeacon::entropy(), H256::zero());
+	});
+}
+
+#[test]
+fn revealed_commitment_is_not_slashed() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(commit(account(1), seed(1)));
+		run_to_block(1 + DELAY);
+		assert_ok!(RandomnessBeacon::reveal(Origin::signed(account(1)), seed(1)));
+		// A new commitment, with a later deadline than the first one.
+		assert_ok!(commit(account(1), seed(2)));
+
+		run_to_block(2 + DELAY + WINDOW);
+		assert!(Commitments::<Test>::contains_key(account(1)));
+		assert_eq!(Balances::free_balance(account(1)), 100 - DEPOSIT);
+		assert_eq!(Balances::reserved_balance(account(1)), DEPOSIT);
+	});
+}
+
+#[test]
+fn offchain_worker_commits_and_reveals() {
+	let (offchain, _) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = KeyStore::new();
+	let who = SyncCryptoStore::sr25519_generate_new(&keystore, KEY_TYPE, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt(Arc::new(keystore)));
+
+	t.execute_with(|| {
+		Balances::make_free_balance_be(&who, 100);
+
+		let next_call = || {
+			let tx = pool_state.write().transactions.pop().expect("a transaction was sent");
+			let tx = Extrinsic::decode(&mut &*tx).unwrap();
+			assert_eq!(tx.signature, Some((0, ())));
+			tx.call
+		};
+
+		RandomnessBeacon::offchain_worker(1);
+		let commitment = match next_call() {
+			Call::RandomnessBeacon(crate::Call::commit { commitment }) => commitment,
+			call => panic!("unexpected call {:?}", call),
+		};
+		// Not sent again while it may still be included.
+		RandomnessBeacon::offchain_worker(2);
+		assert!(pool_state.read().transactions.is_empty());
+
+		assert_ok!(RandomnessBeacon::commit(Origin::signed(who), commitment));
+		assert_eq!(Balances::reserved_balance(who), DEPOSIT);
+
+		// Revealed once `RevealDelay` has passed.
+		RandomnessBeacon::offchain_worker(DELAY);
+		assert!(pool_state.read().transactions.is_empty());
+		RandomnessBeacon::offchain_worker(1 + DELAY);
+		let seed = match next_call() {
+			Call::RandomnessBeacon(crate::Call::reveal { seed }) => seed,
+			call => panic!("unexpected call {:?}", call),
+		};
+
+		run_to_block(1 + DELAY);
+		assert_ok!(RandomnessBeacon::reveal(Origin::signed(who), seed));
+		assert_ne!(RandomnessBeacon::entropy(), H256::zero());
+	});
+}
+
+#[test]
+fn offchain_worker_ignores_outsiders() {
+	let (offchain, _) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	// A keystore without beacon keys.
+	t.register_extension(KeystoreExt(Arc::new(KeyStore::new())));
+
+	t.execute_with(|| {
+		RandomnessBeacon::offchain_worker(1);
+		assert!(pool_state.read().transactions.is_empty());
+	});
+}
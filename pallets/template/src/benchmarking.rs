@@ -2,19 +2,31 @@
 
 use super::*;
 
-#[allow(unused)]
-use crate::Pallet as Template;
-use frame_benchmarking::{benchmarks, whitelisted_caller};
+use crate::Pallet as ParametersModule;
+use frame_benchmarking::benchmarks;
 use frame_system::RawOrigin;
 
 benchmarks! {
-	do_something {
-		let s in 0 .. 100;
-		let caller: T::AccountId = whitelisted_caller();
-	}: _(RawOrigin::Signed(caller), s)
+	where_clause { where T::Key: Default }
+
+	set_parameter {
+		let key = T::Key::default();
+		let (_, value) = T::Bounds::bounds(&key);
+		let origin = T::SetOrigin::successful_origin();
+	}: _<T::Origin>(origin, key, value)
+	verify {
+		assert_eq!(ParametersModule::<T>::parameter(key), value);
+	}
+
+	reset_parameter {
+		let key = T::Key::default();
+		let (_, value) = T::Bounds::bounds(&key);
+		Parameters::<T>::insert(key, value);
+		let origin = T::SetOrigin::successful_origin();
+	}: _<T::Origin>(origin, key)
 	verify {
-		assert_eq!(Something::<T>::get(), Some(s));
+		assert_eq!(ParametersModule::<T>::parameter(key), T::Bounds::default_value(&key));
 	}
 
-	impl_benchmark_test_suite!(Template, crate::mock::new_test_ext(), crate::mock::Test);
+	impl_benchmark_test_suite!(ParametersModule, crate::mock::new_test_ext(), crate::mock::Test);
 }
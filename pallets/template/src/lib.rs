@@ -1,10 +1,20 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-/// Edit this file to define custom logic or remove it if it is not needed.
-/// Learn more about FRAME and the core library of Substrate FRAME pallets:
-/// <https://docs.substrate.io/v3/runtime/frame>
+//! On-chain parameters that root or governance can change without a runtime upgrade.
+//!
+//! The runtime defines the parameter keys, e.g. as an enum, and their bounds through
+//! [`ParameterBounds`]. `SetOrigin` sets a parameter with [`Pallet::set_parameter`], which rejects
+//! values outside of the bounds, and resets it to its default with [`Pallet::reset_parameter`].
+//!
+//! Other pallets read a parameter through the [`ParameterOf`] adapter, which implements `Get`.
+//! Storage bounded by a parameter, e.g. a `BoundedVec`, must keep a compile-time bound instead,
+//! and that bound should be the upper end of the parameter's bounds.
+
+// Re-export pallet items so that they can be accessed from the crate namespace.
 pub use pallet::*;
 
+pub mod migrations;
+
 #[cfg(test)]
 mod mock;
 
@@ -14,8 +24,18 @@ mod tests;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 
+/// Defaults and bounds of the parameters, provided by the runtime.
+pub trait ParameterBounds<Key, Value> {
+	/// The value of `key` while it is not set.
+	fn default_value(key: &Key) -> Value;
+
+	/// The lowest and highest value `key` may be set to.
+	fn bounds(key: &Key) -> (Value, Value);
+}
+
 #[frame_support::pallet]
 pub mod pallet {
+	use super::ParameterBounds;
 	use frame_support::pallet_prelude::*;
 	use frame_system::pallet_prelude::*;
 
@@ -24,79 +44,91 @@ pub mod pallet {
 	pub trait Config: frame_system::Config {
 		/// Because this pallet emits events, it depends on the runtime's definition of an event.
 		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// Identifies a parameter.
+		type Key: Parameter + Member + MaxEncodedLen + Copy;
+
+		/// The value of a parameter.
+		type Value: Parameter + Member + MaxEncodedLen + Copy + Ord;
+
+		/// Defaults and bounds of each parameter.
+		type Bounds: ParameterBounds<Self::Key, Self::Value>;
+
+		/// Origin allowed to set and reset parameters.
+		type SetOrigin: EnsureOrigin<Self::Origin>;
 	}
 
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(2);
+
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
-	// The pallet's runtime storage items.
-	// https://docs.substrate.io/v3/runtime/storage
+	/// Parameters that were set, by key. Parameters that are not set have their default value.
 	#[pallet::storage]
-	#[pallet::getter(fn something)]
-	// Learn more about declaring storage items:
-	// https://docs.substrate.io/v3/runtime/storage#declaring-storage-items
-	pub type Something<T> = StorageValue<_, u32>;
+	pub type Parameters<T: Config> = StorageMap<_, Twox64Concat, T::Key, T::Value>;
 
-	// Pallets use events to inform users when important changes are made.
-	// https://docs.substrate.io/v3/runtime/events-and-errors
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
-		/// Event documentation should end with an array that provides descriptive names for event
-		/// parameters. [something, who]
-		SomethingStored(u32, T::AccountId),
+		/// A parameter was set.
+		ParameterSet { key: T::Key, value: T::Value },
+		/// A parameter was reset to its default value.
+		ParameterReset { key: T::Key, value: T::Value },
 	}
 
-	// Errors inform users that something went wrong.
 	#[pallet::error]
 	pub enum Error<T> {
-		/// Error names should be descriptive.
-		NoneValue,
-		/// Errors should have helpful documentation associated with them.
-		StorageOverflow,
+		/// The value is below the lowest value the parameter may be set to.
+		BelowMinimum,
+		/// The value is above the highest value the parameter may be set to.
+		AboveMaximum,
 	}
 
-	// Dispatchable functions allows users to interact with the pallet and invoke state changes.
-	// These functions materialize as "extrinsics", which are often compared to transactions.
-	// Dispatchable functions must be annotated with a weight and must return a DispatchResult.
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		/// An example dispatchable that takes a singles value as a parameter, writes the value to
-		/// storage and emits an event. This function must be dispatched by a signed extrinsic.
+		/// Set the parameter `key` to `value`, which must be within the parameter's bounds.
+		#[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
+		pub fn set_parameter(origin: OriginFor<T>, key: T::Key, value: T::Value) -> DispatchResult {
+			T::SetOrigin::ensure_origin(origin)?;
+
+			let (min, max) = T::Bounds::bounds(&key);
+			ensure!(value >= min, Error::<T>::BelowMinimum);
+			ensure!(value <= max, Error::<T>::AboveMaximum);
+			Parameters::<T>::insert(key, value);
+
+			Self::deposit_event(Event::ParameterSet { key, value });
+			Ok(())
+		}
+
+		/// Reset the parameter `key` to its default value.
 		#[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
-		pub fn do_something(origin: OriginFor<T>, something: u32) -> DispatchResult {
-			// Check that the extrinsic was signed and get the signer.
-			// This function will return an error if the extrinsic is not signed.
-			// https://docs.substrate.io/v3/runtime/origins
-			let who = ensure_signed(origin)?;
-
-			// Update storage.
-			<Something<T>>::put(something);
-
-			// Emit an event.
-			Self::deposit_event(Event::SomethingStored(something, who));
-			// Return a successful DispatchResultWithPostInfo
+		pub fn reset_parameter(origin: OriginFor<T>, key: T::Key) -> DispatchResult {
+			T::SetOrigin::ensure_origin(origin)?;
+
+			Parameters::<T>::remove(key);
+
+			let value = T::Bounds::default_value(&key);
+			Self::deposit_event(Event::ParameterReset { key, value });
 			Ok(())
 		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The current value of the parameter `key`.
+		pub fn parameter(key: T::Key) -> T::Value {
+			Parameters::<T>::get(key).unwrap_or_else(|| T::Bounds::default_value(&key))
+		}
+	}
+
+	/// Reads the parameter `K` as a `Get`, e.g. for the config of another pallet.
+	pub struct ParameterOf<T, K>(PhantomData<(T, K)>);
 
-		/// An example dispatchable that may throw a custom error.
-		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1,1))]
-		pub fn cause_error(origin: OriginFor<T>) -> DispatchResult {
-			let _who = ensure_signed(origin)?;
-
-			// Read a value from storage.
-			match <Something<T>>::get() {
-				// Return an error if the value has not been set.
-				None => return Err(Error::<T>::NoneValue.into()),
-				Some(old) => {
-					// Increment the value read from storage; will error in the event of overflow.
-					let new = old.checked_add(1).ok_or(Error::<T>::StorageOverflow)?;
-					// Update the value in storage with the incremented result.
-					<Something<T>>::put(new);
-					Ok(())
-				},
-			}
+	impl<T: Config, K: Get<T::Key>> Get<T::Value> for ParameterOf<T, K> {
+		fn get() -> T::Value {
+			Pallet::<T>::parameter(K::get())
 		}
 	}
 }
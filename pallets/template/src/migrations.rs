@@ -0,0 +1,84 @@
+//! Storage migrations for the parameters pallet.
+
+/// Version 1: the pallet records its storage version. The layout of its storage is unchanged.
+pub mod v1 {
+	use crate::pallet::{Config, Pallet};
+	use core::marker::PhantomData;
+	use frame_support::{
+		traits::{Get, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+		weights::Weight,
+	};
+
+	const LOG_TARGET: &str = "runtime::template";
+
+	/// Stamps storage version 1 on chains that started before the pallet declared a version.
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() >= 1 {
+				log::info!(target: LOG_TARGET, "MigrateToV1 already applied, skipping");
+				return T::DbWeight::get().reads(1)
+			}
+
+			StorageVersion::new(1).put::<Pallet<T>>();
+
+			log::info!(target: LOG_TARGET, "MigrateToV1 applied");
+			T::DbWeight::get().reads_writes(1, 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			frame_support::ensure!(
+				Pallet::<T>::on_chain_storage_version() >= 1,
+				"storage version not updated"
+			);
+			Ok(())
+		}
+	}
+}
+
+/// Version 2: the pallet stores on-chain parameters. The `Something` value of the template is
+/// removed.
+pub mod v2 {
+	use crate::pallet::{Config, Pallet};
+	use core::marker::PhantomData;
+	use frame_support::{
+		traits::{Get, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+		weights::Weight,
+	};
+
+	const LOG_TARGET: &str = "runtime::template";
+
+	/// `Something` as it was declared up to version 1.
+	#[frame_support::storage_alias]
+	type Something<T: Config> = StorageValue<Pallet<T>, u32>;
+
+	/// Removes the template's `Something` value and stamps storage version 2.
+	pub struct MigrateToV2<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV2<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() >= 2 {
+				log::info!(target: LOG_TARGET, "MigrateToV2 already applied, skipping");
+				return T::DbWeight::get().reads(1)
+			}
+
+			Something::<T>::kill();
+			StorageVersion::new(2).put::<Pallet<T>>();
+
+			log::info!(target: LOG_TARGET, "MigrateToV2 applied");
+			T::DbWeight::get().reads_writes(1, 2)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			frame_support::ensure!(
+				Pallet::<T>::on_chain_storage_version() >= 2,
+				"storage version not updated"
+			);
+			frame_support::ensure!(!Something::<T>::exists(), "`Something` not removed");
+			Ok(())
+		}
+	}
+}
@@ -18,7 +18,7 @@ frame_support::construct_runtime!(
 		UncheckedExtrinsic = UncheckedExtrinsic,
 	{
 		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
-		TemplateModule: pallet_template::{Pallet, Call, Storage, Event<T>},
+		Parameters: pallet_template::{Pallet, Call, Storage, Event<T>},
 	}
 );
 
@@ -49,8 +49,33 @@ impl system::Config for Test {
 	type MaxConsumers = frame_support::traits::ConstU32<16>;
 }
 
+/// The parameter the tests set, with a default of 10 and bounds of 5 and 50.
+pub const LIMIT: u8 = 0;
+
+pub struct TestBounds;
+
+impl pallet_template::ParameterBounds<u8, u32> for TestBounds {
+	fn default_value(key: &u8) -> u32 {
+		match *key {
+			LIMIT => 10,
+			_ => 0,
+		}
+	}
+
+	fn bounds(key: &u8) -> (u32, u32) {
+		match *key {
+			LIMIT => (5, 50),
+			_ => (0, u32::MAX),
+		}
+	}
+}
+
 impl pallet_template::Config for Test {
 	type Event = Event;
+	type Key = u8;
+	type Value = u32;
+	type Bounds = TestBounds;
+	type SetOrigin = frame_system::EnsureRoot<u64>;
 }
 
 // Build genesis storage according to the mock runtime.
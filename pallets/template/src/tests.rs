@@ -1,20 +1,108 @@
-use crate::{mock::*, Error};
-use frame_support::{assert_noop, assert_ok};
+use crate::{mock::*, Error, Event as ParametersEvent};
+use frame_support::{assert_noop, assert_ok, dispatch::DispatchError};
 
 #[test]
-fn it_works_for_default_value() {
+fn parameter_has_default_value_until_set() {
 	new_test_ext().execute_with(|| {
-		// Dispatch a signed extrinsic.
-		assert_ok!(TemplateModule::do_something(Origin::signed(1), 42));
-		// Read pallet storage and assert an expected result.
-		assert_eq!(TemplateModule::something(), Some(42));
+		assert_eq!(Parameters::parameter(LIMIT), 10);
+
+		assert_ok!(Parameters::set_parameter(Origin::root(), LIMIT, 42));
+		assert_eq!(Parameters::parameter(LIMIT), 42);
+	});
+}
+
+#[test]
+fn set_parameter_emits_event() {
+	new_test_ext().execute_with(|| {
+		// Events are not emitted in the genesis block.
+		System::set_block_number(1);
+
+		assert_ok!(Parameters::set_parameter(Origin::root(), LIMIT, 42));
+		System::assert_last_event(ParametersEvent::ParameterSet { key: LIMIT, value: 42 }.into());
+	});
+}
+
+#[test]
+fn set_parameter_checks_bounds() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Parameters::set_parameter(Origin::root(), LIMIT, 4),
+			Error::<Test>::BelowMinimum
+		);
+		assert_noop!(
+			Parameters::set_parameter(Origin::root(), LIMIT, 51),
+			Error::<Test>::AboveMaximum
+		);
+		assert_ok!(Parameters::set_parameter(Origin::root(), LIMIT, 5));
+		assert_ok!(Parameters::set_parameter(Origin::root(), LIMIT, 50));
+	});
+}
+
+#[test]
+fn only_set_origin_can_change_parameters() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Parameters::set_parameter(Origin::signed(1), LIMIT, 42),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Parameters::reset_parameter(Origin::signed(1), LIMIT),
+			DispatchError::BadOrigin
+		);
 	});
 }
 
 #[test]
-fn correct_error_for_none_value() {
+fn reset_parameter_restores_default() {
 	new_test_ext().execute_with(|| {
-		// Ensure the expected error is thrown when no value is present.
-		assert_noop!(TemplateModule::cause_error(Origin::signed(1)), Error::<Test>::NoneValue);
+		System::set_block_number(1);
+		assert_ok!(Parameters::set_parameter(Origin::root(), LIMIT, 42));
+
+		assert_ok!(Parameters::reset_parameter(Origin::root(), LIMIT));
+		assert_eq!(Parameters::parameter(LIMIT), 10);
+		System::assert_last_event(ParametersEvent::ParameterReset { key: LIMIT, value: 10 }.into());
+	});
+}
+
+#[test]
+fn parameter_of_reads_current_value() {
+	use frame_support::traits::{ConstU8, Get};
+
+	new_test_ext().execute_with(|| {
+		type Limit = crate::ParameterOf<Test, ConstU8<LIMIT>>;
+
+		assert_eq!(Limit::get(), 10);
+		assert_ok!(Parameters::set_parameter(Origin::root(), LIMIT, 42));
+		assert_eq!(Limit::get(), 42);
+	});
+}
+
+#[test]
+fn migration_to_v1_sets_storage_version() {
+	use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<Parameters>();
+		crate::migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+		assert_eq!(Parameters::on_chain_storage_version(), 1);
+	});
+}
+
+#[test]
+fn migration_to_v2_removes_something() {
+	use frame_support::{
+		storage::unhashed,
+		traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+	};
+
+	new_test_ext().execute_with(|| {
+		let something = frame_support::storage::storage_prefix(b"Parameters", b"Something");
+		unhashed::put(&something, &42u32);
+		StorageVersion::new(1).put::<Parameters>();
+
+		crate::migrations::v2::MigrateToV2::<Test>::on_runtime_upgrade();
+
+		assert_eq!(unhashed::get::<u32>(&something), None);
+		assert_eq!(Parameters::on_chain_storage_version(), 2);
 	});
 }
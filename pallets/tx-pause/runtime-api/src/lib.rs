@@ -0,0 +1,13 @@
+//! Runtime API definition for the tx-pause pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	/// Queries against `pallet_tx_pause`.
+	pub trait TxPauseApi {
+		/// All paused calls, as `(pallet name, call name)` pairs.
+		fn paused_calls() -> Vec<(Vec<u8>, Vec<u8>)>;
+	}
+}
@@ -0,0 +1,143 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Pause individual calls without a runtime upgrade.
+//!
+//! `PauseOrigin` pauses and unpauses calls by the name of their pallet, as declared in
+//! `construct_runtime!`, and the name of the call, e.g. `("FileStorage", "create_file")`. The
+//! pallet implements `Contains<Call>` and is meant to be the runtime's `BaseCallFilter`, which
+//! then rejects every paused call. Calls matched by `WhitelistedCalls` can never be paused.
+
+// Re-export pallet items so that they can be accessed from the crate namespace.
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{CallMetadata, Contains, GetCallMetadata},
+	};
+	use frame_system::pallet_prelude::*;
+	use sp_std::vec::Vec;
+
+	/// Name of a pallet, as declared in `construct_runtime!`.
+	pub type PalletNameOf<T> = BoundedVec<u8, <T as Config>::MaxNameLen>;
+
+	/// Name of a call of a pallet.
+	pub type CallNameOf<T> = BoundedVec<u8, <T as Config>::MaxNameLen>;
+
+	/// Full name of a call: the name of its pallet and of the call itself.
+	pub type FullNameOf<T> = (PalletNameOf<T>, CallNameOf<T>);
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Configure the pallet by specifying the parameters and types on which it depends.
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// The overarching call type, whose names are checked against the paused calls.
+		type Call: Parameter + GetCallMetadata;
+
+		/// Origin allowed to pause and unpause calls.
+		type PauseOrigin: EnsureOrigin<Self::Origin>;
+
+		/// Calls that can never be paused.
+		type WhitelistedCalls: Contains<FullNameOf<Self>>;
+
+		/// The maximum length of a pallet or call name.
+		#[pallet::constant]
+		type MaxNameLen: Get<u32>;
+	}
+
+	/// The set of paused calls.
+	#[pallet::storage]
+	pub type PausedCalls<T: Config> = StorageMap<_, Blake2_128Concat, FullNameOf<T>, ()>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A call was paused.
+		CallPaused { full_name: FullNameOf<T> },
+		/// A call was unpaused.
+		CallUnpaused { full_name: FullNameOf<T> },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The call is already paused.
+		IsPaused,
+		/// The call is not paused.
+		IsUnpaused,
+		/// The call is whitelisted and cannot be paused.
+		Unpausable,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Pause the call `full_name` until it is unpaused.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn pause(origin: OriginFor<T>, full_name: FullNameOf<T>) -> DispatchResult {
+			T::PauseOrigin::ensure_origin(origin)?;
+
+			ensure!(!T::WhitelistedCalls::contains(&full_name), Error::<T>::Unpausable);
+			ensure!(!PausedCalls::<T>::contains_key(&full_name), Error::<T>::IsPaused);
+			PausedCalls::<T>::insert(&full_name, ());
+
+			Self::deposit_event(Event::CallPaused { full_name });
+			Ok(())
+		}
+
+		/// Unpause the call `full_name`.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn unpause(origin: OriginFor<T>, full_name: FullNameOf<T>) -> DispatchResult {
+			T::PauseOrigin::ensure_origin(origin)?;
+
+			ensure!(PausedCalls::<T>::take(&full_name).is_some(), Error::<T>::IsUnpaused);
+
+			Self::deposit_event(Event::CallUnpaused { full_name });
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Whether the call `function_name` of `pallet_name` is paused.
+		pub fn is_paused(pallet_name: &[u8], function_name: &[u8]) -> bool {
+			let full_name = match (
+				PalletNameOf::<T>::try_from(pallet_name.to_vec()),
+				CallNameOf::<T>::try_from(function_name.to_vec()),
+			) {
+				(Ok(pallet_name), Ok(function_name)) => (pallet_name, function_name),
+				// Names that do not fit cannot have been paused.
+				_ => return false,
+			};
+
+			!T::WhitelistedCalls::contains(&full_name) && PausedCalls::<T>::contains_key(&full_name)
+		}
+
+		/// All paused calls, as `(pallet name, call name)` pairs.
+		pub fn paused_calls() -> Vec<(Vec<u8>, Vec<u8>)> {
+			PausedCalls::<T>::iter_keys()
+				.map(|(pallet_name, function_name)| {
+					(pallet_name.into_inner(), function_name.into_inner())
+				})
+				.collect()
+		}
+	}
+
+	impl<T: Config> Contains<<T as Config>::Call> for Pallet<T> {
+		/// Whether the call may be dispatched, i.e. is not paused.
+		fn contains(call: &<T as Config>::Call) -> bool {
+			let CallMetadata { pallet_name, function_name } = call.get_call_metadata();
+			!Self::is_paused(pallet_name.as_bytes(), function_name.as_bytes())
+		}
+	}
+}
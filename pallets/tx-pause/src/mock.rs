@@ -0,0 +1,83 @@
+use crate as pallet_tx_pause;
+use frame_support::traits::{ConstU16, ConstU32, ConstU64, Contains};
+use frame_system as system;
+use sp_core::H256;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		TxPause: pallet_tx_pause::{Pallet, Call, Storage, Event<T>},
+	}
+);
+
+impl system::Config for Test {
+	type BaseCallFilter = TxPause;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+/// Whitelists the calls of `TxPause` and `System::set_code`.
+pub struct Whitelist;
+
+impl Contains<pallet_tx_pause::FullNameOf<Test>> for Whitelist {
+	fn contains((pallet_name, function_name): &pallet_tx_pause::FullNameOf<Test>) -> bool {
+		match (pallet_name.as_slice(), function_name.as_slice()) {
+			(b"TxPause", _) | (b"System", b"set_code") => true,
+			_ => false,
+		}
+	}
+}
+
+impl pallet_tx_pause::Config for Test {
+	type Event = Event;
+	type Call = Call;
+	type PauseOrigin = frame_system::EnsureRoot<u64>;
+	type WhitelistedCalls = Whitelist;
+	type MaxNameLen = ConstU32<16>;
+}
+
+/// Full name of the call `function_name` of `pallet_name`.
+pub fn full_name(pallet_name: &str, function_name: &str) -> pallet_tx_pause::FullNameOf<Test> {
+	(
+		pallet_name.as_bytes().to_vec().try_into().unwrap(),
+		function_name.as_bytes().to_vec().try_into().unwrap(),
+	)
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+}
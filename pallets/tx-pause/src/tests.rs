@@ -0,0 +1,105 @@
+use crate::{mock::*, Error, Event as TxPauseEvent};
+use frame_support::{assert_noop, assert_ok, dispatch::DispatchError, traits::Contains};
+use sp_runtime::traits::Dispatchable;
+
+fn remark() -> Call {
+	Call::System(frame_system::Call::remark { remark: vec![] })
+}
+
+fn fill_block() -> Call {
+	Call::System(frame_system::Call::fill_block { ratio: Default::default() })
+}
+
+#[test]
+fn pause_rejects_the_call_until_unpaused() {
+	new_test_ext().execute_with(|| {
+		// Events are not emitted in the genesis block.
+		System::set_block_number(1);
+		assert!(TxPause::contains(&remark()));
+
+		assert_ok!(TxPause::pause(Origin::root(), full_name("System", "remark")));
+		System::assert_last_event(
+			TxPauseEvent::CallPaused { full_name: full_name("System", "remark") }.into(),
+		);
+		assert!(TxPause::is_paused(b"System", b"remark"));
+		assert!(!TxPause::contains(&remark()));
+		// Other calls of the same pallet are unaffected.
+		assert!(TxPause::contains(&fill_block()));
+		assert_eq!(TxPause::paused_calls(), vec![(b"System".to_vec(), b"remark".to_vec())]);
+
+		assert_ok!(TxPause::unpause(Origin::root(), full_name("System", "remark")));
+		System::assert_last_event(
+			TxPauseEvent::CallUnpaused { full_name: full_name("System", "remark") }.into(),
+		);
+		assert!(TxPause::contains(&remark()));
+		assert_eq!(TxPause::paused_calls(), vec![]);
+	});
+}
+
+#[test]
+fn paused_calls_are_filtered_on_dispatch() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(TxPause::pause(Origin::root(), full_name("System", "remark")));
+
+		assert_noop!(
+			remark().dispatch(Origin::signed(1)),
+			frame_system::Error::<Test>::CallFiltered
+		);
+		assert_ok!(fill_block().dispatch(Origin::root()));
+	});
+}
+
+#[test]
+fn pause_and_unpause_require_pause_origin() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			TxPause::pause(Origin::signed(1), full_name("System", "remark")),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(TxPause::pause(Origin::root(), full_name("System", "remark")));
+		assert_noop!(
+			TxPause::unpause(Origin::signed(1), full_name("System", "remark")),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn pause_and_unpause_check_current_state() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			TxPause::unpause(Origin::root(), full_name("System", "remark")),
+			Error::<Test>::IsUnpaused
+		);
+		assert_ok!(TxPause::pause(Origin::root(), full_name("System", "remark")));
+		assert_noop!(
+			TxPause::pause(Origin::root(), full_name("System", "remark")),
+			Error::<Test>::IsPaused
+		);
+	});
+}
+
+#[test]
+fn whitelisted_calls_cannot_be_paused() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			TxPause::pause(Origin::root(), full_name("TxPause", "unpause")),
+			Error::<Test>::Unpausable
+		);
+		assert_noop!(
+			TxPause::pause(Origin::root(), full_name("System", "set_code")),
+			Error::<Test>::Unpausable
+		);
+
+		// Even if paused before being whitelisted, e.g. by an older runtime.
+		crate::PausedCalls::<Test>::insert(full_name("System", "set_code"), ());
+		assert!(!TxPause::is_paused(b"System", b"set_code"));
+	});
+}
+
+#[test]
+fn names_too_long_to_pause_are_never_paused() {
+	new_test_ext().execute_with(|| {
+		assert!(!TxPause::is_paused(b"System", b"a_call_name_longer_than_sixteen_bytes"));
+	});
+}
@@ -0,0 +1,258 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Proof-of-authority validator set.
+//!
+//! Root or governance adds and removes validators with [`Pallet::add_validator`] and
+//! [`Pallet::remove_validator`]. The pallet is the `SessionManager` of `pallet_session`, so a
+//! change is picked up at the next session rotation and becomes active, in Aura and GRANDPA, in
+//! the session after it. A new validator must register its keys with `session.setKeys` before it
+//! is activated, otherwise the session pallet leaves it out of the authority set.
+//!
+//! The pallet is also the offence handler of `pallet_offences`. An offending validator, e.g. one
+//! reported for a GRANDPA equivocation, is disabled right away and removed from the set, unless
+//! that would leave fewer than `MinAuthorities` validators.
+//!
+//! A chain that ran Aura and GRANDPA before this pallet seeds the set from its current
+//! authorities with [`migrations::v1::SeedFromAuthorities`].
+
+// Re-export pallet items so that they can be accessed from the crate namespace.
+pub use pallet::*;
+
+pub mod migrations;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use frame_support::{pallet_prelude::*, traits::Contains, weights::Weight};
+	use frame_system::pallet_prelude::*;
+	use sp_runtime::{traits::Convert, Perbill};
+	use sp_staking::{
+		offence::{DisableStrategy, OffenceDetails, OnOffenceHandler},
+		SessionIndex,
+	};
+	use sp_std::{marker::PhantomData, vec::Vec};
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Configure the pallet by specifying the parameters and types on which it depends.
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_session::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// Origin allowed to add and remove validators.
+		type AddRemoveOrigin: EnsureOrigin<Self::Origin>;
+
+		/// The number of validators that must remain in the set.
+		#[pallet::constant]
+		type MinAuthorities: Get<u32>;
+
+		/// The maximum number of validators in the set.
+		#[pallet::constant]
+		type MaxAuthorities: Get<u32>;
+	}
+
+	/// The current validator set, in the order it is handed to the session pallet.
+	#[pallet::storage]
+	#[pallet::getter(fn validators)]
+	pub type Validators<T: Config> =
+		StorageValue<_, BoundedVec<T::AccountId, T::MaxAuthorities>, ValueQuery>;
+
+	/// Whether `Validators` changed since it was last handed to the session pallet.
+	#[pallet::storage]
+	pub(super) type ValidatorsChanged<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A validator was added; it becomes active two sessions later.
+		ValidatorAdded { validator: T::AccountId },
+		/// A validator was removed; it stops validating two sessions later.
+		ValidatorRemoved { validator: T::AccountId },
+		/// A validator was reported for an offence and disabled for the rest of the session.
+		/// `removed` tells whether it was also taken out of the set.
+		OffenderDisabled { validator: T::AccountId, removed: bool },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The account is already a validator.
+		AlreadyValidator,
+		/// The account is not a validator.
+		NotValidator,
+		/// Removing the validator would leave fewer than `MinAuthorities`.
+		TooFewValidators,
+		/// Adding the validator would exceed `MaxAuthorities`.
+		TooManyValidators,
+	}
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		pub initial_validators: Vec<T::AccountId>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { initial_validators: Vec::new() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			let validators: BoundedVec<_, _> = self
+				.initial_validators
+				.clone()
+				.try_into()
+				.expect("initial validators must not exceed MaxAuthorities");
+			Validators::<T>::put(validators);
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Add `validator` to the set, starting two sessions from now.
+		///
+		/// The validator must also set its session keys with `session.setKeys`.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 2))]
+		pub fn add_validator(origin: OriginFor<T>, validator: T::AccountId) -> DispatchResult {
+			T::AddRemoveOrigin::ensure_origin(origin)?;
+
+			Validators::<T>::try_mutate(|validators| -> DispatchResult {
+				ensure!(!validators.contains(&validator), Error::<T>::AlreadyValidator);
+				validators
+					.try_push(validator.clone())
+					.map_err(|_| Error::<T>::TooManyValidators)?;
+				Ok(())
+			})?;
+			ValidatorsChanged::<T>::put(true);
+
+			Self::deposit_event(Event::ValidatorAdded { validator });
+			Ok(())
+		}
+
+		/// Remove `validator` from the set, starting two sessions from now.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 2))]
+		pub fn remove_validator(origin: OriginFor<T>, validator: T::AccountId) -> DispatchResult {
+			T::AddRemoveOrigin::ensure_origin(origin)?;
+
+			Validators::<T>::try_mutate(|validators| -> DispatchResult {
+				let index = validators
+					.iter()
+					.position(|v| *v == validator)
+					.ok_or(Error::<T>::NotValidator)?;
+				ensure!(
+					validators.len() > T::MinAuthorities::get() as usize,
+					Error::<T>::TooFewValidators
+				);
+				validators.remove(index);
+				Ok(())
+			})?;
+			ValidatorsChanged::<T>::put(true);
+
+			Self::deposit_event(Event::ValidatorRemoved { validator });
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Disable `validator` in the current session and take it out of the set if possible.
+		fn punish_offender(validator: &T::AccountId) {
+			if let Some(id) = T::ValidatorIdOf::convert(validator.clone()) {
+				pallet_session::Pallet::<T>::disable(&id);
+			}
+
+			let removed = Validators::<T>::mutate(|validators| {
+				match validators.iter().position(|v| v == validator) {
+					Some(index) if validators.len() > T::MinAuthorities::get() as usize => {
+						validators.remove(index);
+						true
+					},
+					_ => false,
+				}
+			});
+			if removed {
+				ValidatorsChanged::<T>::put(true);
+			}
+
+			Self::deposit_event(Event::OffenderDisabled { validator: validator.clone(), removed });
+		}
+	}
+
+	impl<T: Config> pallet_session::SessionManager<T::AccountId> for Pallet<T> {
+		fn new_session(_new_index: u32) -> Option<Vec<T::AccountId>> {
+			if ValidatorsChanged::<T>::take() {
+				Some(Validators::<T>::get().into_inner())
+			} else {
+				None
+			}
+		}
+
+		fn new_session_genesis(_new_index: u32) -> Option<Vec<T::AccountId>> {
+			Some(Validators::<T>::get().into_inner())
+		}
+
+		fn end_session(_end_index: u32) {}
+
+		fn start_session(_start_index: u32) {}
+	}
+
+	impl<T: Config> pallet_session::historical::SessionManager<T::AccountId, T::AccountId>
+		for Pallet<T>
+	{
+		fn new_session(new_index: u32) -> Option<Vec<(T::AccountId, T::AccountId)>> {
+			<Self as pallet_session::SessionManager<_>>::new_session(new_index)
+				.map(|validators| validators.into_iter().map(|v| (v.clone(), v)).collect())
+		}
+
+		fn new_session_genesis(new_index: u32) -> Option<Vec<(T::AccountId, T::AccountId)>> {
+			<Self as pallet_session::SessionManager<_>>::new_session_genesis(new_index)
+				.map(|validators| validators.into_iter().map(|v| (v.clone(), v)).collect())
+		}
+
+		fn end_session(_end_index: u32) {}
+
+		fn start_session(_start_index: u32) {}
+	}
+
+	impl<T: Config, FullIdentification>
+		OnOffenceHandler<T::AccountId, (T::AccountId, FullIdentification), Weight> for Pallet<T>
+	{
+		fn on_offence(
+			offenders: &[OffenceDetails<T::AccountId, (T::AccountId, FullIdentification)>],
+			_slash_fraction: &[Perbill],
+			_session: SessionIndex,
+			_disable_strategy: DisableStrategy,
+		) -> Weight {
+			for details in offenders {
+				Self::punish_offender(&details.offender.0);
+			}
+
+			T::DbWeight::get().reads_writes(1, 2).saturating_mul(offenders.len() as Weight)
+		}
+	}
+
+	impl<T: Config> Contains<T::AccountId> for Pallet<T> {
+		/// Whether `who` is in the validator set.
+		fn contains(who: &T::AccountId) -> bool {
+			Validators::<T>::get().contains(who)
+		}
+	}
+
+	/// Maps an account to its validator id, which is the account itself.
+	pub struct ValidatorOf<T>(PhantomData<T>);
+
+	impl<T: Config> Convert<T::AccountId, Option<T::AccountId>> for ValidatorOf<T> {
+		fn convert(account: T::AccountId) -> Option<T::AccountId> {
+			Some(account)
+		}
+	}
+}
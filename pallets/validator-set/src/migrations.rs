@@ -0,0 +1,89 @@
+//! Storage migrations for the validator set pallet.
+
+/// Version 1: the validator set moves from the genesis authorities of Aura and GRANDPA to this
+/// pallet and `pallet_session`.
+pub mod v1 {
+	use crate::pallet::{Config, Validators};
+	use core::marker::PhantomData;
+	use frame_support::{
+		traits::{Get, OnRuntimeUpgrade},
+		weights::Weight,
+		BoundedVec,
+	};
+	use sp_runtime::traits::{Convert, OpaqueKeys};
+	use sp_std::vec::Vec;
+
+	const LOG_TARGET: &str = "runtime::validator-set";
+
+	/// Seeds `Validators` and the session keys from `Authorities`, the validators of a chain that
+	/// ran Aura and GRANDPA without sessions, with their accounts and current keys.
+	///
+	/// Without it the first session rotation after the upgrade starts from an empty set, and the
+	/// first `add_validator` would make its account the only validator. The migration does nothing
+	/// once `Validators` is set.
+	pub struct SeedFromAuthorities<T, Authorities>(PhantomData<(T, Authorities)>);
+
+	impl<T, Authorities> OnRuntimeUpgrade for SeedFromAuthorities<T, Authorities>
+	where
+		T: Config,
+		Authorities: Get<Vec<(T::AccountId, T::Keys)>>,
+	{
+		fn on_runtime_upgrade() -> Weight {
+			if !Validators::<T>::get().is_empty() {
+				log::info!(target: LOG_TARGET, "SeedFromAuthorities already applied, skipping");
+				return T::DbWeight::get().reads(1)
+			}
+
+			let authorities = Authorities::get();
+			let accounts: Vec<_> = authorities.iter().map(|(account, _)| account.clone()).collect();
+			let validators: BoundedVec<_, T::MaxAuthorities> = match accounts.try_into() {
+				Ok(validators) => validators,
+				Err(_) => {
+					log::error!(target: LOG_TARGET, "more authorities than MaxAuthorities");
+					return T::DbWeight::get().reads(1)
+				},
+			};
+
+			let mut writes = 3u64;
+			let mut queued = Vec::with_capacity(authorities.len());
+			for (account, keys) in authorities {
+				let id = match T::ValidatorIdOf::convert(account.clone()) {
+					Some(id) => id,
+					None => continue,
+				};
+				// As in the genesis build of `pallet_session`, which these keys bypass.
+				for key_type in T::Keys::key_ids() {
+					pallet_session::KeyOwner::<T>::insert(
+						(*key_type, keys.get_raw(*key_type).to_vec()),
+						&id,
+					);
+				}
+				pallet_session::NextKeys::<T>::insert(&id, &keys);
+				if frame_system::Pallet::<T>::inc_consumers_without_limit(&account).is_err() {
+					frame_system::Pallet::<T>::inc_providers(&account);
+				}
+				writes += T::Keys::key_ids().len() as u64 + 2;
+				queued.push((id, keys));
+			}
+
+			log::info!(target: LOG_TARGET, "SeedFromAuthorities seeded {} validators", queued.len());
+			pallet_session::Validators::<T>::put(
+				queued.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>(),
+			);
+			pallet_session::QueuedKeys::<T>::put(queued);
+			Validators::<T>::put(validators);
+
+			T::DbWeight::get().reads_writes(1, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			frame_support::ensure!(!Validators::<T>::get().is_empty(), "no validators");
+			frame_support::ensure!(
+				pallet_session::Validators::<T>::get().len() == Validators::<T>::get().len(),
+				"session validators differ"
+			);
+			Ok(())
+		}
+	}
+}
@@ -0,0 +1,109 @@
+use crate as pallet_validator_set;
+use frame_support::{
+	parameter_types,
+	traits::{ConstU16, ConstU32, ConstU64, GenesisBuild, Hooks},
+};
+use frame_system as system;
+use sp_core::H256;
+use sp_runtime::{
+	testing::{Header, UintAuthorityId},
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		ValidatorSet: pallet_validator_set::{Pallet, Call, Config<T>, Storage, Event<T>},
+		Session: pallet_session::{Pallet, Call, Config<T>, Storage, Event},
+	}
+);
+
+impl system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+/// Sessions last this many blocks.
+pub const PERIOD: u64 = 5;
+
+impl pallet_session::Config for Test {
+	type Event = Event;
+	type ValidatorId = u64;
+	type ValidatorIdOf = pallet_validator_set::ValidatorOf<Self>;
+	type ShouldEndSession = pallet_session::PeriodicSessions<ConstU64<PERIOD>, ConstU64<0>>;
+	type NextSessionRotation = pallet_session::PeriodicSessions<ConstU64<PERIOD>, ConstU64<0>>;
+	type SessionManager = ValidatorSet;
+	type SessionHandler = pallet_session::TestSessionHandler;
+	type Keys = UintAuthorityId;
+	type WeightInfo = ();
+}
+
+impl pallet_validator_set::Config for Test {
+	type Event = Event;
+	type AddRemoveOrigin = frame_system::EnsureRoot<u64>;
+	type MinAuthorities = ConstU32<2>;
+	type MaxAuthorities = ConstU32<4>;
+}
+
+parameter_types! {
+	/// The authorities `SeedFromAuthorities` finds on the chain.
+	pub LegacyAuthorities: Vec<(u64, UintAuthorityId)> =
+		vec![(1, UintAuthorityId(1)), (2, UintAuthorityId(2))];
+}
+
+/// Build genesis storage with `validators` in the set, each with its own id as session key.
+///
+/// Without validators, sessions are not set up at all, as on a chain that ran without them.
+pub fn new_test_ext(validators: &[u64]) -> sp_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	if !validators.is_empty() {
+		pallet_validator_set::GenesisConfig::<Test> { initial_validators: validators.to_vec() }
+			.assimilate_storage(&mut t)
+			.unwrap();
+		pallet_session::GenesisConfig::<Test> {
+			keys: validators.iter().map(|&v| (v, v, UintAuthorityId(v))).collect(),
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+	}
+	t.into()
+}
+
+/// Run blocks up to and including `n`, rotating sessions on the way.
+pub fn run_to_block(n: u64) {
+	while System::block_number() < n {
+		let next = System::block_number() + 1;
+		System::set_block_number(next);
+		Session::on_initialize(next);
+	}
+}
@@ -0,0 +1,168 @@
+use crate::{migrations::v1::SeedFromAuthorities, mock::*, Error, Event as ValidatorSetEvent};
+use frame_support::{
+	assert_noop, assert_ok, dispatch::DispatchError, traits::OnRuntimeUpgrade, weights::Weight,
+};
+use sp_runtime::{key_types::DUMMY, testing::UintAuthorityId, Perbill};
+use sp_staking::offence::{DisableStrategy, OffenceDetails, OnOffenceHandler};
+
+/// Report `offender` for an offence in the current session.
+fn report(offender: u64) {
+	<ValidatorSet as OnOffenceHandler<u64, (u64, u64), Weight>>::on_offence(
+		&[OffenceDetails { offender: (offender, offender), reporters: vec![] }],
+		&[Perbill::from_percent(100)],
+		Session::current_index(),
+		DisableStrategy::Always,
+	);
+}
+
+#[test]
+fn genesis_validators_are_active() {
+	new_test_ext(&[1, 2, 3]).execute_with(|| {
+		assert_eq!(ValidatorSet::validators().into_inner(), vec![1, 2, 3]);
+		assert_eq!(Session::validators(), vec![1, 2, 3]);
+	});
+}
+
+#[test]
+fn added_validator_is_active_two_sessions_later() {
+	new_test_ext(&[1, 2, 3]).execute_with(|| {
+		// Events are not emitted in the genesis block.
+		System::set_block_number(1);
+		frame_system::Pallet::<Test>::inc_providers(&4);
+		assert_ok!(Session::set_keys(Origin::signed(4), UintAuthorityId(4), vec![]));
+
+		assert_ok!(ValidatorSet::add_validator(Origin::root(), 4));
+		System::assert_last_event(ValidatorSetEvent::ValidatorAdded { validator: 4 }.into());
+		assert_eq!(ValidatorSet::validators().into_inner(), vec![1, 2, 3, 4]);
+
+		// Queued at the next rotation and active at the one after.
+		run_to_block(PERIOD);
+		assert_eq!(Session::validators(), vec![1, 2, 3]);
+		assert!(Session::queued_keys().iter().any(|(v, _)| *v == 4));
+		run_to_block(2 * PERIOD);
+		assert_eq!(Session::validators(), vec![1, 2, 3, 4]);
+	});
+}
+
+#[test]
+fn removed_validator_is_inactive_two_sessions_later() {
+	new_test_ext(&[1, 2, 3]).execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(ValidatorSet::remove_validator(Origin::root(), 2));
+		System::assert_last_event(ValidatorSetEvent::ValidatorRemoved { validator: 2 }.into());
+
+		run_to_block(PERIOD);
+		assert_eq!(Session::validators(), vec![1, 2, 3]);
+		run_to_block(2 * PERIOD);
+		assert_eq!(Session::validators(), vec![1, 3]);
+	});
+}
+
+#[test]
+fn add_and_remove_check_the_set() {
+	new_test_ext(&[1, 2, 3]).execute_with(|| {
+		assert_noop!(ValidatorSet::add_validator(Origin::signed(1), 4), DispatchError::BadOrigin);
+		assert_noop!(
+			ValidatorSet::add_validator(Origin::root(), 3),
+			Error::<Test>::AlreadyValidator
+		);
+		assert_ok!(ValidatorSet::add_validator(Origin::root(), 4));
+		assert_noop!(
+			ValidatorSet::add_validator(Origin::root(), 5),
+			Error::<Test>::TooManyValidators
+		);
+
+		assert_noop!(
+			ValidatorSet::remove_validator(Origin::root(), 5),
+			Error::<Test>::NotValidator
+		);
+	});
+}
+
+#[test]
+fn remove_respects_min_authorities() {
+	new_test_ext(&[1, 2, 3]).execute_with(|| {
+		assert_ok!(ValidatorSet::remove_validator(Origin::root(), 1));
+		assert_noop!(
+			ValidatorSet::remove_validator(Origin::root(), 2),
+			Error::<Test>::TooFewValidators
+		);
+		assert_eq!(ValidatorSet::validators().into_inner(), vec![2, 3]);
+	});
+}
+
+#[test]
+fn unchanged_set_is_not_handed_to_the_session_again() {
+	new_test_ext(&[1, 2, 3]).execute_with(|| {
+		run_to_block(3 * PERIOD);
+		assert_eq!(Session::current_index(), 3);
+		assert_eq!(Session::validators(), vec![1, 2, 3]);
+	});
+}
+
+#[test]
+fn offender_is_disabled_and_removed() {
+	new_test_ext(&[1, 2, 3]).execute_with(|| {
+		System::set_block_number(1);
+		report(3);
+
+		System::assert_last_event(
+			ValidatorSetEvent::OffenderDisabled { validator: 3, removed: true }.into(),
+		);
+		// Disabled right away, by its index in the active set.
+		assert_eq!(Session::disabled_validators(), vec![2]);
+		assert_eq!(ValidatorSet::validators().into_inner(), vec![1, 2]);
+
+		run_to_block(2 * PERIOD);
+		assert_eq!(Session::validators(), vec![1, 2]);
+	});
+}
+
+#[test]
+fn offender_stays_in_the_set_at_min_authorities() {
+	new_test_ext(&[1, 2]).execute_with(|| {
+		System::set_block_number(1);
+		report(2);
+
+		System::assert_last_event(
+			ValidatorSetEvent::OffenderDisabled { validator: 2, removed: false }.into(),
+		);
+		assert_eq!(Session::disabled_validators(), vec![1]);
+		assert_eq!(ValidatorSet::validators().into_inner(), vec![1, 2]);
+	});
+}
+
+#[test]
+fn migration_seeds_the_set_from_legacy_authorities() {
+	new_test_ext(&[]).execute_with(|| {
+		SeedFromAuthorities::<Test, LegacyAuthorities>::on_runtime_upgrade();
+
+		assert_eq!(ValidatorSet::validators().into_inner(), vec![1, 2]);
+		assert_eq!(Session::validators(), vec![1, 2]);
+		assert_eq!(Session::queued_keys(), vec![(1, UintAuthorityId(1)), (2, UintAuthorityId(2))]);
+		assert_eq!(pallet_session::NextKeys::<Test>::get(1), Some(UintAuthorityId(1)));
+		assert_eq!(
+			pallet_session::KeyOwner::<Test>::get((DUMMY, 2u64.to_le_bytes().to_vec())),
+			Some(2)
+		);
+
+		// The set survives session rotations, and a new validator joins it.
+		run_to_block(2 * PERIOD);
+		assert_eq!(Session::validators(), vec![1, 2]);
+		frame_system::Pallet::<Test>::inc_providers(&3);
+		assert_ok!(Session::set_keys(Origin::signed(3), UintAuthorityId(3), vec![]));
+		assert_ok!(ValidatorSet::add_validator(Origin::root(), 3));
+		run_to_block(4 * PERIOD);
+		assert_eq!(Session::validators(), vec![1, 2, 3]);
+	});
+}
+
+#[test]
+fn migration_leaves_an_existing_set_alone() {
+	new_test_ext(&[3, 4]).execute_with(|| {
+		SeedFromAuthorities::<Test, LegacyAuthorities>::on_runtime_upgrade();
+
+		assert_eq!(ValidatorSet::validators().into_inner(), vec![3, 4]);
+		assert_eq!(Session::validators(), vec![3, 4]);
+	});
+}
@@ -0,0 +1,68 @@
+//! Chain extension giving ink! contracts read access to the file storage and PoE pallets.
+//!
+//! A contract calls a function by its `func_id` with the SCALE-encoded input below, and gets the
+//! SCALE-encoded output back:
+//!
+//! - [`FILE`] takes a `cid: Hash` and returns the `Option<File>` stored for it in `Files`.
+//! - [`CAN_DOWNLOAD`] takes a `(cid: Hash, who: AccountId)` and returns a `bool`, whether `who`
+//!   may download the file, the same check the file gateway makes.
+//! - [`CLAIM`] takes a `claim: Hash` and returns the PoE claim, an
+//!   `Option<(AccountId, BlockNumber, u64)>` of its owner, block and time.
+//!
+//! Every function charges the weight of its storage reads.
+
+use crate::{AccountId, FileStorage, Hash, PoeModule, Runtime};
+use codec::Encode;
+use frame_support::{
+	traits::Get,
+	weights::{constants::RocksDbWeight, Weight},
+};
+use pallet_contracts::chain_extension::{
+	ChainExtension, Environment, Ext, InitState, RetVal, SysConfig, UncheckedFrom,
+};
+use sp_runtime::DispatchError;
+
+/// `func_id` of the read of a `Files` entry.
+pub const FILE: u32 = 1;
+/// `func_id` of the download check of an account for a file.
+pub const CAN_DOWNLOAD: u32 = 2;
+/// `func_id` of the read of a PoE claim.
+pub const CLAIM: u32 = 3;
+
+/// The chain extension of the runtime's contracts.
+pub struct FilesExtension;
+
+impl ChainExtension<Runtime> for FilesExtension {
+	fn call<E>(func_id: u32, env: Environment<E, InitState>) -> Result<RetVal, DispatchError>
+	where
+		E: Ext<T = Runtime>,
+		<E::T as SysConfig>::AccountId: UncheckedFrom<<E::T as SysConfig>::Hash> + AsRef<[u8]>,
+	{
+		let mut env = env.buf_in_buf_out();
+		let output = match func_id {
+			FILE => {
+				env.charge_weight(reads(1))?;
+				let cid: Hash = env.read_as()?;
+				FileStorage::files(cid).encode()
+			},
+			CAN_DOWNLOAD => {
+				env.charge_weight(reads(2))?;
+				let (cid, who): (Hash, AccountId) = env.read_as()?;
+				FileStorage::can_download(&cid, &who).encode()
+			},
+			CLAIM => {
+				env.charge_weight(reads(1))?;
+				let claim: Hash = env.read_as()?;
+				PoeModule::claims(claim).encode()
+			},
+			_ => return Err(DispatchError::Other("FilesExtension: unknown func_id")),
+		};
+		env.write(&output, false, None)?;
+
+		Ok(RetVal::Converging(0))
+	}
+}
+
+fn reads(count: Weight) -> Weight {
+	RocksDbWeight::get().reads(count)
+}
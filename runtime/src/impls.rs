@@ -0,0 +1,435 @@
+//! Auxiliary `struct`/`enum`s for the runtime.
+
+use crate::{
+	opaque::SessionKeys, AccountId, Aura, Balance, Balances, Call, Grandpa, MaxFileOwned,
+	MaxLength, ParameterKey, Parameters, Runtime, Timestamp, Treasury, TxPause, MILLIUNIT,
+};
+use frame_support::{
+	inherent::ProvideInherent,
+	storage::{storage_prefix, unhashed},
+	traits::{Contains, Currency, FindAuthor, Get, Imbalance, OnRuntimeUpgrade, OnUnbalanced},
+	weights::{
+		constants::{ExtrinsicBaseWeight, RocksDbWeight},
+		Weight, WeightToFeeCoefficient, WeightToFeeCoefficients, WeightToFeePolynomial,
+	},
+};
+use smallvec::smallvec;
+#[cfg(feature = "try-runtime")]
+use sp_core::hashing::twox_128;
+use sp_runtime::Perbill;
+use sp_std::vec::Vec;
+
+type NegativeImbalance = <Balances as Currency<AccountId>>::NegativeImbalance;
+
+/// Resolves the author of the current block from its Aura pre-runtime digest.
+pub struct AuraAccountAdapter;
+
+impl FindAuthor<AccountId> for AuraAccountAdapter {
+	fn find_author<'a, I>(digests: I) -> Option<AccountId>
+	where
+		I: 'a + IntoIterator<Item = (sp_runtime::ConsensusEngineId, &'a [u8])>,
+	{
+		pallet_aura::AuraAuthorId::<Runtime>::find_author(digests)
+			.and_then(|authority| AccountId::try_from(authority.as_ref()).ok())
+	}
+}
+
+/// Credits the block author, or the treasury if the author cannot be resolved.
+pub struct Author;
+
+impl OnUnbalanced<NegativeImbalance> for Author {
+	fn on_nonzero_unbalanced(amount: NegativeImbalance) {
+		let digest = frame_system::Pallet::<Runtime>::digest();
+		let pre_runtime_digests = digest.logs.iter().filter_map(|d| d.as_pre_runtime());
+
+		match AuraAccountAdapter::find_author(pre_runtime_digests) {
+			Some(author) => Balances::resolve_creating(&author, amount),
+			None => Treasury::on_unbalanced(amount),
+		}
+	}
+}
+
+/// Splits transaction fees between the treasury and the block author.
+///
+/// 80% of the fee goes to the treasury and 20% to the author, who also receives the whole tip.
+pub struct DealWithFees;
+
+impl OnUnbalanced<NegativeImbalance> for DealWithFees {
+	fn on_unbalanceds<B>(mut fees_then_tips: impl Iterator<Item = NegativeImbalance>) {
+		if let Some(fees) = fees_then_tips.next() {
+			let (to_treasury, mut to_author) = fees.ration(80, 20);
+			if let Some(tips) = fees_then_tips.next() {
+				tips.merge_into(&mut to_author);
+			}
+			Treasury::on_unbalanced(to_treasury);
+			Author::on_unbalanced(to_author);
+		}
+	}
+}
+
+/// Pallets whose calls can never be paused: system, timestamp, sudo and governance calls, and
+/// the calls to unpause.
+pub struct UnpausablePallets;
+
+impl Contains<pallet_tx_pause::FullNameOf<Runtime>> for UnpausablePallets {
+	fn contains(full_name: &pallet_tx_pause::FullNameOf<Runtime>) -> bool {
+		const UNPAUSABLE: &[&[u8]] = &[
+			b"System",
+			b"Timestamp",
+			b"Sudo",
+			b"Council",
+			b"Democracy",
+			b"Scheduler",
+			b"Preimage",
+			b"TxPause",
+		];
+
+		UNPAUSABLE.contains(&full_name.0.as_slice())
+	}
+}
+
+/// The runtime's base call filter: inherents always pass, other calls unless they are paused.
+///
+/// Every block must include some inherents, e.g. the timestamp. A paused inherent would make every
+/// block invalid, so no block could ever unpause it, whatever `UnpausablePallets` says.
+pub struct CallFilter;
+
+impl Contains<Call> for CallFilter {
+	fn contains(call: &Call) -> bool {
+		is_inherent(call) || TxPause::contains(call)
+	}
+}
+
+/// Whether `call` is an inherent of one of the pallets of the runtime that provide inherents.
+fn is_inherent(call: &Call) -> bool {
+	match call {
+		Call::Timestamp(call) => <Timestamp as ProvideInherent>::is_inherent(call),
+		_ => false,
+	}
+}
+
+/// Converts dispatch weight into a fee.
+///
+/// The curve is linear and calibrated so that `ExtrinsicBaseWeight`, the weight of the smallest
+/// extrinsic, costs one `MILLIUNIT` before the fee multiplier is applied.
+pub struct WeightToFee;
+
+impl WeightToFeePolynomial for WeightToFee {
+	type Balance = Balance;
+
+	fn polynomial() -> WeightToFeeCoefficients<Self::Balance> {
+		let p = MILLIUNIT;
+		let q = Balance::from(ExtrinsicBaseWeight::get());
+		smallvec![WeightToFeeCoefficient {
+			degree: 1,
+			negative: false,
+			coeff_frac: Perbill::from_rational(p % q, q),
+			coeff_integer: p / q,
+		}]
+	}
+}
+
+/// Removes the storage left behind by `pallet_randomness_collective_flip`, which was replaced by
+/// `RandomnessBeacon` in spec version 111.
+///
+/// Only the first upgrade past that version finds anything to remove. Drop this from
+/// `Migrations` once every chain runs spec version 111 or later.
+pub struct RemoveRandomnessCollectiveFlip;
+
+impl RemoveRandomnessCollectiveFlip {
+	fn key() -> [u8; 32] {
+		storage_prefix(b"RandomnessCollectiveFlip", b"RandomMaterial")
+	}
+}
+
+impl OnRuntimeUpgrade for RemoveRandomnessCollectiveFlip {
+	fn on_runtime_upgrade() -> Weight {
+		if !unhashed::exists(&Self::key()) {
+			return RocksDbWeight::get().reads(1)
+		}
+		unhashed::kill(&Self::key());
+		RocksDbWeight::get().reads_writes(1, 1)
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn post_upgrade() -> Result<(), &'static str> {
+		frame_support::ensure!(!unhashed::exists(&Self::key()), "RandomMaterial was not removed");
+		Ok(())
+	}
+}
+
+/// The Aura and GRANDPA authorities of a chain from before `ValidatorSet` and `Session`, for
+/// `pallet_validator_set::migrations::v1::SeedFromAuthorities`.
+///
+/// Both lists were set up from the same validators in the same order, so they are paired by
+/// position, and each validator's account is the one of its Aura key, as for block authors.
+pub struct LegacyAuthorities;
+
+impl Get<Vec<(AccountId, SessionKeys)>> for LegacyAuthorities {
+	fn get() -> Vec<(AccountId, SessionKeys)> {
+		Aura::authorities()
+			.into_iter()
+			.zip(Grandpa::grandpa_authorities())
+			.filter_map(|(aura, (grandpa, _weight))| {
+				let account = AccountId::try_from(aura.as_ref()).ok()?;
+				Some((account, SessionKeys { aura, grandpa }))
+			})
+			.collect()
+	}
+}
+
+/// Defaults and bounds of the on-chain parameters.
+///
+/// The upper bounds of the file parameters are the hard caps that bound the file storage, and the
+/// minimum link length cannot exceed the maximum.
+pub struct ParameterBounds;
+
+impl pallet_template::ParameterBounds<ParameterKey, u32> for ParameterBounds {
+	fn default_value(key: &ParameterKey) -> u32 {
+		match key {
+			ParameterKey::FileOwnedLimit => 9999,
+			ParameterKey::MinLinkLength => 5,
+			ParameterKey::MaxLinkLength => 50,
+		}
+	}
+
+	fn bounds(key: &ParameterKey) -> (u32, u32) {
+		match key {
+			ParameterKey::FileOwnedLimit => (1, MaxFileOwned::get()),
+			ParameterKey::MinLinkLength =>
+				(1, Parameters::parameter(ParameterKey::MaxLinkLength)),
+			ParameterKey::MaxLinkLength =>
+				(Parameters::parameter(ParameterKey::MinLinkLength), MaxLength::get()),
+		}
+	}
+}
+
+/// Removes the storage left behind by the template pallet, which holds the on-chain parameters
+/// as `Parameters` since spec version 113.
+///
+/// Only the first upgrade past that version finds anything to remove. Drop this from
+/// `Migrations` once every chain runs spec version 113 or later.
+pub struct RemoveTemplateModule;
+
+impl RemoveTemplateModule {
+	fn keys() -> [[u8; 32]; 2] {
+		[
+			storage_prefix(b"TemplateModule", b"Something"),
+			storage_prefix(b"TemplateModule", b":__STORAGE_VERSION__:"),
+		]
+	}
+}
+
+impl OnRuntimeUpgrade for RemoveTemplateModule {
+	fn on_runtime_upgrade() -> Weight {
+		let keys = Self::keys();
+		if !keys.iter().any(|key| unhashed::exists(key)) {
+			return RocksDbWeight::get().reads(keys.len() as u64)
+		}
+		keys.iter().for_each(|key| unhashed::kill(key));
+		RocksDbWeight::get().reads_writes(keys.len() as u64, keys.len() as u64)
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn pre_upgrade() -> Result<(), &'static str> {
+		// The template pallet had no other storage, so nothing is left behind.
+		let prefix = twox_128(b"TemplateModule");
+		let mut key = prefix.to_vec();
+		while let Some(next) = frame_support::sp_io::storage::next_key(&key) {
+			if !next.starts_with(&prefix) {
+				break
+			}
+			frame_support::ensure!(
+				Self::keys().iter().any(|known| next == known[..]),
+				"TemplateModule has storage this migration does not remove"
+			);
+			key = next;
+		}
+		Ok(())
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn post_upgrade() -> Result<(), &'static str> {
+		frame_support::ensure!(
+			!Self::keys().iter().any(|key| unhashed::exists(key)),
+			"TemplateModule storage was not removed"
+		);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		AdjustmentVariable, BlockWeights, MinimumMultiplier, Origin, SlowAdjustingFeeUpdate,
+		System, TargetBlockFullness, UNIT,
+	};
+	use codec::Encode;
+	use frame_support::{
+		traits::GenesisBuild,
+		weights::{DispatchClass, WeightToFee as _},
+	};
+	use pallet_transaction_payment::Multiplier;
+	use sp_consensus_aura::{Slot, AURA_ENGINE_ID};
+	use sp_core::sr25519::Public;
+	use sp_runtime::{traits::Convert, DigestItem, FixedPointNumber};
+
+	fn max_normal() -> Weight {
+		BlockWeights::get()
+			.get(DispatchClass::Normal)
+			.max_total
+			.unwrap_or_else(|| BlockWeights::get().max_block)
+	}
+
+	fn target() -> Weight {
+		TargetBlockFullness::get() * max_normal()
+	}
+
+	/// Runs `assertions` in a block whose normal class consumed `weight`.
+	fn run_with_system_weight<F: FnMut()>(weight: Weight, mut assertions: F) {
+		let mut t: sp_io::TestExternalities = frame_system::GenesisConfig::default()
+			.build_storage::<Runtime>()
+			.unwrap()
+			.into();
+		t.execute_with(|| {
+			System::set_block_consumed_resources(weight, 0);
+			assertions()
+		});
+	}
+
+	#[test]
+	fn base_extrinsic_costs_a_milliunit() {
+		let fee = WeightToFee::weight_to_fee(&ExtrinsicBaseWeight::get());
+		assert!(fee.max(MILLIUNIT) - fee.min(MILLIUNIT) < MILLIUNIT / 1_000);
+	}
+
+	#[test]
+	fn multiplier_is_stable_at_target_fullness() {
+		run_with_system_weight(target(), || {
+			let multiplier = Multiplier::saturating_from_rational(3, 2);
+			assert_eq!(SlowAdjustingFeeUpdate::<Runtime>::convert(multiplier), multiplier);
+		});
+	}
+
+	#[test]
+	fn full_blocks_raise_multiplier() {
+		run_with_system_weight(max_normal(), || {
+			let mut multiplier = Multiplier::one();
+			for _ in 0..100 {
+				let next = SlowAdjustingFeeUpdate::<Runtime>::convert(multiplier);
+				assert!(next > multiplier);
+				multiplier = next;
+			}
+
+			// Each completely full block raises fees by roughly `AdjustmentVariable * 75%`.
+			let single = SlowAdjustingFeeUpdate::<Runtime>::convert(Multiplier::one());
+			let expected = AdjustmentVariable::get()
+				.saturating_mul(Multiplier::saturating_from_rational(3, 4));
+			assert!(single.saturating_sub(Multiplier::one()) >= expected);
+		});
+	}
+
+	#[test]
+	fn empty_blocks_lower_multiplier_down_to_minimum() {
+		run_with_system_weight(0, || {
+			let mut multiplier = Multiplier::one();
+			for _ in 0..100 {
+				let next = SlowAdjustingFeeUpdate::<Runtime>::convert(multiplier);
+				assert!(next < multiplier);
+				multiplier = next;
+			}
+
+			// An empty block lowers the multiplier in proportion to its value and never below the
+			// minimum, so one just above the minimum falls but need not reach it in one step.
+			let minimum = MinimumMultiplier::get();
+			let just_above =
+				minimum.saturating_add(Multiplier::saturating_from_rational(1, 1_000_000));
+			let next = SlowAdjustingFeeUpdate::<Runtime>::convert(just_above);
+			assert!(next < just_above);
+			assert!(next >= minimum);
+			assert_eq!(SlowAdjustingFeeUpdate::<Runtime>::convert(minimum), minimum);
+		});
+	}
+
+	/// Runs `assertions` in a block authored by the Aura authority `author`, if any.
+	fn run_with_author<F: FnOnce()>(author: Option<[u8; 32]>, assertions: F) {
+		let mut storage =
+			frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+		let authorities =
+			author.into_iter().map(|author| Public::from_raw(author).into()).collect();
+		pallet_aura::GenesisConfig::<Runtime> { authorities }
+			.assimilate_storage(&mut storage)
+			.unwrap();
+		sp_io::TestExternalities::new(storage).execute_with(|| {
+			System::deposit_log(DigestItem::PreRuntime(AURA_ENGINE_ID, Slot::from(0).encode()));
+			assertions()
+		});
+	}
+
+	#[test]
+	fn fees_are_split_between_treasury_and_author() {
+		run_with_author(Some([7; 32]), || {
+			DealWithFees::on_unbalanceds(vec![Balances::issue(100 * UNIT)].into_iter());
+
+			assert_eq!(Balances::free_balance(Treasury::account_id()), 80 * UNIT);
+			assert_eq!(Balances::free_balance(AccountId::new([7; 32])), 20 * UNIT);
+		});
+	}
+
+	#[test]
+	fn tips_go_to_the_author() {
+		run_with_author(Some([7; 32]), || {
+			let (fees, tips) = (Balances::issue(100 * UNIT), Balances::issue(10 * UNIT));
+			DealWithFees::on_unbalanceds(vec![fees, tips].into_iter());
+
+			assert_eq!(Balances::free_balance(Treasury::account_id()), 80 * UNIT);
+			assert_eq!(Balances::free_balance(AccountId::new([7; 32])), 30 * UNIT);
+		});
+	}
+
+	#[test]
+	fn fees_and_tips_go_to_the_treasury_without_an_author() {
+		run_with_author(None, || {
+			let (fees, tips) = (Balances::issue(100 * UNIT), Balances::issue(10 * UNIT));
+			DealWithFees::on_unbalanceds(vec![fees, tips].into_iter());
+
+			assert_eq!(Balances::free_balance(Treasury::account_id()), 110 * UNIT);
+		});
+	}
+
+	fn full_name(pallet: &str, call: &str) -> pallet_tx_pause::FullNameOf<Runtime> {
+		(
+			pallet.as_bytes().to_vec().try_into().unwrap(),
+			call.as_bytes().to_vec().try_into().unwrap(),
+		)
+	}
+
+	#[test]
+	fn timestamp_cannot_be_paused() {
+		run_with_system_weight(0, || {
+			assert_eq!(
+				TxPause::pause(Origin::root(), full_name("Timestamp", "set")),
+				Err(pallet_tx_pause::Error::<Runtime>::Unpausable.into())
+			);
+		});
+	}
+
+	#[test]
+	fn inherents_pass_the_call_filter_even_if_paused() {
+		run_with_system_weight(0, || {
+			// As if paused before `Timestamp` was whitelisted.
+			pallet_tx_pause::PausedCalls::<Runtime>::insert(full_name("Timestamp", "set"), ());
+			pallet_tx_pause::PausedCalls::<Runtime>::insert(full_name("Balances", "transfer"), ());
+
+			let set = Call::Timestamp(pallet_timestamp::Call::set { now: 1 });
+			assert!(CallFilter::contains(&set));
+
+			let transfer = Call::Balances(pallet_balances::Call::transfer {
+				dest: AccountId::new([1; 32]).into(),
+				value: 1,
+			});
+			assert!(!CallFilter::contains(&transfer));
+		});
+	}
+}
@@ -0,0 +1,401 @@
+//! Portable proof-of-existence certificates.
+//!
+//! A [`PoeCertificate`] bundles everything a third party needs to check that a claim was
+//! recorded in `pallet_poe::Claims` of a finalized block, without talking to any node:
+//!
+//! - the header of the finalized block,
+//! - a storage read proof of the claim entry against the header's state root,
+//! - the GRANDPA justification finalizing that header.
+//!
+//! [`verify`] checks a certificate against a GRANDPA authority set the verifier already trusts.
+//! The node exports certificates with its `export-poe-certificate` subcommand and the
+//! `poe-verify` binary of this crate checks them.
+
+use codec::{Decode, Encode};
+use finality_grandpa::voter_set::VoterSet;
+use sp_core::{
+	crypto::AccountId32,
+	hashing::{blake2_128, twox_128},
+	H256,
+};
+use sp_finality_grandpa::{AuthorityId, AuthorityList, AuthoritySignature, SetId};
+use sp_runtime::{
+	generic,
+	traits::{BlakeTwo256, Header as HeaderT},
+};
+use sp_trie::StorageProof;
+use std::{
+	collections::{HashMap, HashSet},
+	fmt,
+};
+
+/// Name of the PoE pallet in the runtime's `construct_runtime!`.
+pub const POE_PALLET_PREFIX: &[u8] = b"PoeModule";
+/// Name of the storage map holding the claims.
+pub const CLAIMS_STORAGE_PREFIX: &[u8] = b"Claims";
+
+/// Block number type of the chain.
+pub type BlockNumber = u32;
+/// Hash type of the chain.
+pub type Hash = H256;
+/// Header type of the chain.
+pub type Header = generic::Header<BlockNumber, BlakeTwo256>;
+
+/// A self-contained proof that a claim exists in a finalized block.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct PoeCertificate {
+	/// Header of the block the claim is proven against.
+	pub header: Header,
+	/// The claimed hash, i.e. the key of the `Claims` entry.
+	pub claim: Hash,
+	/// Trie nodes proving the `Claims` entry against `header.state_root`.
+	pub proof: Vec<Vec<u8>>,
+	/// SCALE-encoded GRANDPA justification finalizing `header`.
+	pub justification: Vec<u8>,
+}
+
+impl PoeCertificate {
+	/// Decode a certificate from its `0x`-prefixed hex representation.
+	pub fn from_hex(hex: &str) -> Result<Self, Error> {
+		let bytes = sp_core::bytes::from_hex(hex.trim()).map_err(|_| Error::InvalidHex)?;
+		Self::decode(&mut &bytes[..]).map_err(Error::Codec)
+	}
+
+	/// Encode the certificate as a `0x`-prefixed hex string.
+	pub fn to_hex(&self) -> String {
+		sp_core::bytes::to_hex(&self.encode(), false)
+	}
+}
+
+/// A GRANDPA authority set trusted by the verifier.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthoritySet {
+	/// The id of the set, as returned by `GrandpaApi::current_set_id`.
+	pub set_id: SetId,
+	/// The authorities and their voting weights.
+	pub authorities: AuthorityList,
+}
+
+/// The facts established by a successfully verified certificate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifiedClaim {
+	/// The claimed hash.
+	pub claim: Hash,
+	/// The account owning the claim.
+	pub owner: AccountId32,
+	/// The block number the claim was created at.
+	pub created_at: BlockNumber,
+	/// The time the claim was created at, in milliseconds since the Unix epoch.
+	///
+	/// This is 0 for claims created before creation times were recorded.
+	pub timestamp: u64,
+	/// Hash of the finalized block the claim was proven against.
+	pub block_hash: Hash,
+	/// Number of the finalized block the claim was proven against.
+	pub block_number: BlockNumber,
+}
+
+/// Reasons a certificate can be rejected.
+#[derive(Debug)]
+pub enum Error {
+	/// The certificate is not valid hex.
+	InvalidHex,
+	/// Some part of the certificate could not be decoded.
+	Codec(codec::Error),
+	/// The trusted authority set is empty or has zero total weight.
+	EmptyAuthoritySet,
+	/// The justification does not finalize the certificate's header.
+	JustificationTargetMismatch,
+	/// A precommit in the justification is not signed by the trusted authority set.
+	BadSignature,
+	/// The precommits do not reach the supermajority threshold.
+	InvalidCommit,
+	/// The vote ancestries of the justification are inconsistent.
+	InvalidAncestry,
+	/// The storage proof does not match the header's state root.
+	InvalidProof(String),
+	/// The storage proof shows that the claim does not exist.
+	ClaimNotFound,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Error::InvalidHex => write!(f, "certificate is not valid hex"),
+			Error::Codec(e) => write!(f, "failed to decode certificate: {}", e),
+			Error::EmptyAuthoritySet => write!(f, "trusted authority set is empty"),
+			Error::JustificationTargetMismatch =>
+				write!(f, "justification does not finalize the certified block"),
+			Error::BadSignature => write!(f, "justification contains an invalid signature"),
+			Error::InvalidCommit =>
+				write!(f, "justification is not backed by a supermajority of the authority set"),
+			Error::InvalidAncestry => write!(f, "justification has invalid vote ancestries"),
+			Error::InvalidProof(e) => write!(f, "invalid storage proof: {}", e),
+			Error::ClaimNotFound => write!(f, "claim does not exist in the certified block"),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// The storage key of the `Claims` entry for `claim`.
+///
+/// `Claims` is a `Blake2_128Concat` map, so the key is the pallet and storage prefixes followed
+/// by the hashed and raw encoding of the claim.
+pub fn claim_storage_key(claim: &Hash) -> Vec<u8> {
+	let encoded = claim.encode();
+	let mut key = Vec::with_capacity(32 + 16 + encoded.len());
+	key.extend_from_slice(&twox_128(POE_PALLET_PREFIX));
+	key.extend_from_slice(&twox_128(CLAIMS_STORAGE_PREFIX));
+	key.extend_from_slice(&blake2_128(&encoded));
+	key.extend_from_slice(&encoded);
+	key
+}
+
+/// Verify `certificate` against the trusted `authorities`.
+///
+/// This checks that the justification finalizes the certificate's header with a supermajority of
+/// valid signatures from `authorities`, and that the storage proof shows the claim in the state
+/// of that header.
+pub fn verify(
+	certificate: &PoeCertificate,
+	authorities: &AuthoritySet,
+) -> Result<VerifiedClaim, Error> {
+	let block_hash = certificate.header.hash();
+	let block_number = *certificate.header.number();
+
+	verify_justification(&certificate.justification, block_hash, block_number, authorities)?;
+
+	let key = claim_storage_key(&certificate.claim);
+	let proof = StorageProof::new(certificate.proof.iter().cloned());
+	let values = sp_state_machine::read_proof_check::<BlakeTwo256, _>(
+		*certificate.header.state_root(),
+		proof,
+		[&key],
+	)
+	.map_err(|e| Error::InvalidProof(e.to_string()))?;
+	let value = values.get(&key).cloned().flatten().ok_or(Error::ClaimNotFound)?;
+	let (owner, created_at, timestamp) =
+		<(AccountId32, BlockNumber, u64)>::decode(&mut &value[..]).map_err(Error::Codec)?;
+
+	Ok(VerifiedClaim {
+		claim: certificate.claim,
+		owner,
+		created_at,
+		timestamp,
+		block_hash,
+		block_number,
+	})
+}
+
+/// A GRANDPA justification, as stored by the node alongside finalized blocks.
+#[derive(Encode, Decode)]
+struct GrandpaJustification {
+	round: u64,
+	commit: finality_grandpa::Commit<Hash, BlockNumber, AuthoritySignature, AuthorityId>,
+	votes_ancestries: Vec<Header>,
+}
+
+fn verify_justification(
+	encoded: &[u8],
+	block_hash: Hash,
+	block_number: BlockNumber,
+	authorities: &AuthoritySet,
+) -> Result<(), Error> {
+	let justification = GrandpaJustification::decode(&mut &encoded[..]).map_err(Error::Codec)?;
+
+	if (justification.commit.target_hash, justification.commit.target_number) !=
+		(block_hash, block_number)
+	{
+		return Err(Error::JustificationTargetMismatch)
+	}
+
+	let voters = VoterSet::new(authorities.authorities.iter().cloned())
+		.ok_or(Error::EmptyAuthoritySet)?;
+	let ancestry_chain = AncestryChain::new(&justification.votes_ancestries);
+
+	match finality_grandpa::validate_commit(&justification.commit, &voters, &ancestry_chain) {
+		Ok(ref result) if result.is_valid() => {},
+		_ => return Err(Error::InvalidCommit),
+	}
+
+	let mut buf = Vec::new();
+	let mut visited_hashes = HashSet::new();
+	for signed in justification.commit.precommits.iter() {
+		if !sp_finality_grandpa::check_message_signature_with_buffer(
+			&finality_grandpa::Message::Precommit(signed.precommit.clone()),
+			&signed.id,
+			&signed.signature,
+			justification.round,
+			authorities.set_id,
+			&mut buf,
+		) {
+			return Err(Error::BadSignature)
+		}
+
+		if block_hash == signed.precommit.target_hash {
+			continue
+		}
+
+		let route = ancestry_chain
+			.route(block_hash, signed.precommit.target_hash)
+			.ok_or(Error::InvalidAncestry)?;
+		// ancestry starts from parent hash but the precommit target hash has been visited
+		visited_hashes.insert(signed.precommit.target_hash);
+		visited_hashes.extend(route);
+	}
+
+	// every header in the ancestries must be needed by some precommit
+	let ancestry_hashes: HashSet<_> =
+		justification.votes_ancestries.iter().map(|h| h.hash()).collect();
+	if visited_hashes != ancestry_hashes {
+		return Err(Error::InvalidAncestry)
+	}
+
+	Ok(())
+}
+
+/// The headers shipped with a justification, indexed by hash.
+struct AncestryChain {
+	ancestry: HashMap<Hash, Header>,
+}
+
+impl AncestryChain {
+	fn new(ancestry: &[Header]) -> Self {
+		Self { ancestry: ancestry.iter().map(|h| (h.hash(), h.clone())).collect() }
+	}
+
+	/// The hashes strictly between `base` and `block`, walking back from `block`.
+	fn route(&self, base: Hash, block: Hash) -> Option<Vec<Hash>> {
+		let mut route = Vec::new();
+		let mut current_hash = block;
+		while current_hash != base {
+			current_hash = *self.ancestry.get(&current_hash)?.parent_hash();
+			route.push(current_hash);
+		}
+		route.pop(); // remove the base
+		Some(route)
+	}
+}
+
+impl finality_grandpa::Chain<Hash, BlockNumber> for AncestryChain {
+	fn ancestry(&self, base: Hash, block: Hash) -> Result<Vec<Hash>, finality_grandpa::Error> {
+		self.route(base, block).ok_or(finality_grandpa::Error::NotDescendent)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_keyring::Ed25519Keyring;
+	use sp_state_machine::{Backend, InMemoryBackend};
+	use std::collections::BTreeMap;
+
+	const SET_ID: SetId = 3;
+	const ROUND: u64 = 7;
+	const ALL: [Ed25519Keyring; 3] =
+		[Ed25519Keyring::Alice, Ed25519Keyring::Bob, Ed25519Keyring::Charlie];
+
+	fn authority_set() -> AuthoritySet {
+		AuthoritySet {
+			set_id: SET_ID,
+			authorities: ALL.iter().map(|k| (k.public().into(), 1)).collect(),
+		}
+	}
+
+	fn justification_for(header: &Header, signers: &[Ed25519Keyring], set_id: SetId) -> Vec<u8> {
+		let precommit = finality_grandpa::Precommit {
+			target_hash: header.hash(),
+			target_number: header.number,
+		};
+		let message = finality_grandpa::Message::Precommit(precommit.clone());
+		let payload = sp_finality_grandpa::localized_payload(ROUND, set_id, &message);
+		let precommits = signers
+			.iter()
+			.map(|signer| finality_grandpa::SignedPrecommit {
+				precommit: precommit.clone(),
+				signature: signer.sign(&payload).into(),
+				id: signer.public().into(),
+			})
+			.collect();
+
+		GrandpaJustification {
+			round: ROUND,
+			commit: finality_grandpa::Commit {
+				target_hash: header.hash(),
+				target_number: header.number,
+				precommits,
+			},
+			votes_ancestries: vec![],
+		}
+		.encode()
+	}
+
+	fn certificate(signers: &[Ed25519Keyring], set_id: SetId) -> PoeCertificate {
+		let claim = H256::repeat_byte(0x42);
+		let key = claim_storage_key(&claim);
+		let owner: AccountId32 = sp_keyring::Sr25519Keyring::Alice.to_account_id();
+		let storage: BTreeMap<_, _> =
+			vec![(key.clone(), (owner, 5u32, 1_660_000_000_000u64).encode())].into_iter().collect();
+		let backend = InMemoryBackend::<BlakeTwo256>::from((storage, Default::default()));
+		let state_root = backend.storage_root(std::iter::empty(), Default::default()).0;
+		let proof = sp_state_machine::prove_read(backend, [&key]).unwrap();
+
+		let header = Header::new(
+			10,
+			Default::default(),
+			state_root,
+			Default::default(),
+			Default::default(),
+		);
+		let justification = justification_for(&header, signers, set_id);
+
+		PoeCertificate { header, claim, proof: proof.into_iter_nodes().collect(), justification }
+	}
+
+	#[test]
+	fn claim_storage_key_matches_blake2_128_concat_layout() {
+		let claim = H256::repeat_byte(1);
+		let key = claim_storage_key(&claim);
+
+		assert_eq!(&key[..16], &twox_128(b"PoeModule"));
+		assert_eq!(&key[16..32], &twox_128(b"Claims"));
+		assert_eq!(&key[32..48], &blake2_128(claim.as_bytes()));
+		assert_eq!(&key[48..], claim.as_bytes());
+	}
+
+	#[test]
+	fn verifies_certificate_signed_by_supermajority() {
+		let certificate = certificate(&ALL, SET_ID);
+
+		let verified = verify(&certificate, &authority_set()).unwrap();
+
+		assert_eq!(verified.owner, sp_keyring::Sr25519Keyring::Alice.to_account_id());
+		assert_eq!(verified.created_at, 5);
+		assert_eq!(verified.timestamp, 1_660_000_000_000);
+		assert_eq!(verified.block_number, 10);
+		assert_eq!(PoeCertificate::from_hex(&certificate.to_hex()).unwrap(), certificate);
+	}
+
+	#[test]
+	fn rejects_certificate_without_supermajority() {
+		let certificate = certificate(&[Ed25519Keyring::Alice], SET_ID);
+
+		assert!(matches!(verify(&certificate, &authority_set()), Err(Error::InvalidCommit)));
+	}
+
+	#[test]
+	fn rejects_certificate_signed_for_another_set() {
+		let certificate = certificate(&ALL, 0);
+
+		assert!(matches!(verify(&certificate, &authority_set()), Err(Error::BadSignature)));
+	}
+
+	#[test]
+	fn rejects_tampered_claim() {
+		let mut certificate = certificate(&ALL, SET_ID);
+		certificate.claim = H256::repeat_byte(0x43);
+
+		assert!(verify(&certificate, &authority_set()).is_err());
+	}
+}
@@ -0,0 +1,82 @@
+//! Offline verifier for proof-of-existence certificates.
+//!
+//! The trusted authority set is read from a JSON file of the form
+//!
+//! ```json
+//! { "setId": 0, "authorities": ["5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu"] }
+//! ```
+//!
+//! where every authority is a GRANDPA (ed25519) public key, either SS58 encoded or as
+//! `0x`-prefixed hex, and carries a voting weight of 1.
+
+use clap::Parser;
+use poe_certificate::{AuthoritySet, PoeCertificate};
+use serde::Deserialize;
+use sp_core::{crypto::Ss58Codec, ed25519};
+use std::{fs, path::PathBuf};
+
+/// Verify a proof-of-existence certificate without connecting to any node.
+#[derive(Debug, Parser)]
+#[clap(name = "poe-verify")]
+struct Cli {
+	/// Path to the certificate, as written by `node-template export-poe-certificate`.
+	certificate: PathBuf,
+
+	/// Path to a JSON file describing the trusted GRANDPA authority set.
+	#[clap(long)]
+	authorities: PathBuf,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthoritySetFile {
+	set_id: u64,
+	authorities: Vec<String>,
+}
+
+fn parse_authority(key: &str) -> Result<ed25519::Public, String> {
+	if key.starts_with("0x") {
+		let bytes = sp_core::bytes::from_hex(key).map_err(|e| format!("{}: {:?}", key, e))?;
+		let raw: [u8; 32] =
+			bytes.try_into().map_err(|_| format!("{}: expected 32 bytes", key))?;
+		Ok(ed25519::Public::from_raw(raw))
+	} else {
+		ed25519::Public::from_ss58check(key).map_err(|e| format!("{}: {:?}", key, e))
+	}
+}
+
+fn load_authority_set(path: &PathBuf) -> Result<AuthoritySet, String> {
+	let file = fs::read(path).map_err(|e| format!("reading {}: {}", path.display(), e))?;
+	let file: AuthoritySetFile = serde_json::from_slice(&file)
+		.map_err(|e| format!("parsing {}: {}", path.display(), e))?;
+	let authorities = file
+		.authorities
+		.iter()
+		.map(|key| parse_authority(key).map(|public| (public.into(), 1)))
+		.collect::<Result<_, _>>()?;
+
+	Ok(AuthoritySet { set_id: file.set_id, authorities })
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+	let authorities = load_authority_set(&cli.authorities)?;
+	let certificate = fs::read_to_string(&cli.certificate)
+		.map_err(|e| format!("reading {}: {}", cli.certificate.display(), e))?;
+	let certificate = PoeCertificate::from_hex(&certificate).map_err(|e| e.to_string())?;
+
+	let verified = poe_certificate::verify(&certificate, &authorities).map_err(|e| e.to_string())?;
+
+	println!("claim:        {:?}", verified.claim);
+	println!("owner:        {}", verified.owner);
+	println!("created at:   #{}", verified.created_at);
+	println!("created time: {} ms since the Unix epoch", verified.timestamp);
+	println!("finalized in: #{} ({:?})", verified.block_number, verified.block_hash);
+	Ok(())
+}
+
+fn main() {
+	if let Err(e) = run(Cli::parse()) {
+		eprintln!("Certificate rejected: {}", e);
+		std::process::exit(1);
+	}
+}
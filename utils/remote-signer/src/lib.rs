@@ -0,0 +1,519 @@
+//! Remote keystore: the node keeps no secret keys and asks a separate signer process to sign.
+//!
+//! [`RemoteKeystore`] implements `CryptoStore` and `SyncCryptoStore` for the node, which uses it
+//! when started with `--keystore-uri`. The `remote-signer` binary of this crate is a reference
+//! signer, serving the keys of a regular keystore directory with [`serve_unix`] or
+//! [`serve_http`].
+//!
+//! # Protocol
+//!
+//! Every call is one JSON [`Request`] answered by one JSON [`Response`]. Over a Unix socket
+//! (`unix:///path/to/socket`) the request and the response are each a single line terminated by
+//! `\n`. Over HTTP (`http://host:port`) the request is the body of a `POST /` and the response
+//! the body of the reply; the request must carry the signer's token in an
+//! `Authorization: Bearer <token>` header.
+//!
+//! Key types are their four-character ids, e.g. `"aura"` or `"gran"`, and byte strings are
+//! `0x`-prefixed hex. For example, signing with an Aura key, with the request wrapped here:
+//!
+//! ```text
+//! {"method":"sign",
+//!  "params":{"key_type":"aura","scheme":"sr25519","public":"0xd435...","message":"0x2a"}}
+//! {"signature":"0x5c1f..."}
+//! ```
+//!
+//! A request the signer cannot serve is answered with `{"error":"<reason>"}`. VRF signing is not
+//! part of the protocol, so keys used with BABE cannot be held remotely; Aura and GRANDPA keys can.
+//!
+//! Keys are added on the signer's side only, e.g. with `node-template key insert` into its
+//! keystore directory. The protocol has no request to generate or insert keys, and
+//! [`RemoteKeystore`] refuses to do either, so that e.g. `author_insertKey` on the node cannot
+//! replace the keys the node signs with.
+
+use serde::{Deserialize, Serialize};
+use sp_core::{
+	crypto::{CryptoTypeId, CryptoTypePublicPair, KeyTypeId},
+	ecdsa, ed25519, sr25519, Bytes,
+};
+use sp_keystore::{
+	vrf::{VRFSignature, VRFTranscriptData},
+	CryptoStore, Error, SyncCryptoStore,
+};
+use std::{
+	fs,
+	io::{BufRead, BufReader, Write},
+	os::unix::net::UnixStream,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+mod server;
+
+pub use server::{respond, serve_http, serve_unix};
+
+/// How long the node waits for the signer to answer.
+pub const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A signature scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+	/// Schnorrkel/Ristretto sr25519.
+	Sr25519,
+	/// Ed25519.
+	Ed25519,
+	/// ECDSA over secp256k1.
+	Ecdsa,
+}
+
+impl Scheme {
+	const ALL: [Scheme; 3] = [Scheme::Sr25519, Scheme::Ed25519, Scheme::Ecdsa];
+
+	fn crypto_id(self) -> CryptoTypeId {
+		match self {
+			Scheme::Sr25519 => sr25519::CRYPTO_ID,
+			Scheme::Ed25519 => ed25519::CRYPTO_ID,
+			Scheme::Ecdsa => ecdsa::CRYPTO_ID,
+		}
+	}
+
+	fn from_crypto_id(id: CryptoTypeId) -> Option<Self> {
+		Self::ALL.into_iter().find(|scheme| scheme.crypto_id() == id)
+	}
+}
+
+/// A call to the signer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Request {
+	/// The public keys of `scheme` for `key_type`. Answered with [`Response::Keys`].
+	PublicKeys { key_type: String, scheme: Scheme },
+	/// Whether all of the public keys, with their key types, are held. Answered with
+	/// [`Response::Bool`].
+	HasKeys { keys: Vec<(Bytes, String)> },
+	/// Sign `message` with the key `public` of `scheme`. Answered with [`Response::Signature`],
+	/// which is `null` if the key is not held.
+	Sign { key_type: String, scheme: Scheme, public: Bytes, message: Bytes },
+	/// Sign the 32-byte hash `message` with the ECDSA key `public`. Answered with
+	/// [`Response::Signature`], which is `null` if the key is not held.
+	SignPrehashed { key_type: String, public: Bytes, message: Bytes },
+}
+
+/// The answer of the signer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+	/// Public keys.
+	Keys(Vec<Bytes>),
+	/// A yes or no answer.
+	Bool(bool),
+	/// A signature, if the key is held.
+	Signature(Option<Bytes>),
+	/// The request failed.
+	Error(String),
+}
+
+/// The token in the file at `path`, without surrounding whitespace.
+pub fn read_token(path: &Path) -> Result<String, String> {
+	let token =
+		fs::read_to_string(path).map_err(|e| format!("reading {}: {}", path.display(), e))?;
+	let token = token.trim();
+	if token.is_empty() {
+		return Err(format!("{} holds no token", path.display()))
+	}
+	Ok(token.into())
+}
+
+fn key_type_id(key_type: &str) -> Result<KeyTypeId, String> {
+	KeyTypeId::try_from(key_type).map_err(|()| format!("invalid key type {:?}", key_type))
+}
+
+fn key_type_name(id: KeyTypeId) -> String {
+	String::from_utf8_lossy(&id.0).into_owned()
+}
+
+/// Where the signer listens.
+#[derive(Debug, Clone)]
+enum Endpoint {
+	Unix(PathBuf),
+	Http { url: String, token: String },
+}
+
+/// Keystore of the node delegating to a signer process over a Unix socket or HTTP.
+#[derive(Debug, Clone)]
+pub struct RemoteKeystore {
+	endpoint: Endpoint,
+}
+
+fn transport_error(e: impl std::fmt::Display) -> Error {
+	Error::Other(format!("remote signer: {}", e))
+}
+
+fn unexpected(response: Response) -> Error {
+	Error::Other(format!("remote signer: unexpected response {:?}", response))
+}
+
+fn read_only() -> Error {
+	Error::Other("remote signer: keys can only be added on the signer".into())
+}
+
+impl RemoteKeystore {
+	/// A keystore for the signer at `uri`, either `unix://<path>` or `http://<host>:<port>`.
+	///
+	/// An HTTP signer requires the `token` it was started with, a Unix socket none. The signer is
+	/// contacted on the first request only.
+	pub fn new(uri: &str, token: Option<String>) -> Result<Self, String> {
+		let endpoint = match (uri.strip_prefix("unix://"), token) {
+			(Some(path), None) => Endpoint::Unix(path.into()),
+			(Some(_), Some(_)) => return Err("a token is only used with http://".into()),
+			(None, Some(token)) if uri.starts_with("http://") =>
+				Endpoint::Http { url: uri.into(), token },
+			(None, None) if uri.starts_with("http://") =>
+				return Err(format!("the signer at {} requires a token", uri)),
+			_ =>
+				return Err(format!("expected unix://<path> or http://<host>:<port>, got {}", uri)),
+		};
+		Ok(Self { endpoint })
+	}
+
+	fn call(&self, request: &Request) -> Result<Response, Error> {
+		let response: Response = match &self.endpoint {
+			Endpoint::Unix(path) => {
+				let stream = UnixStream::connect(path).map_err(transport_error)?;
+				stream.set_read_timeout(Some(TIMEOUT)).map_err(transport_error)?;
+				stream.set_write_timeout(Some(TIMEOUT)).map_err(transport_error)?;
+
+				let mut line = serde_json::to_vec(request).map_err(transport_error)?;
+				line.push(b'\n');
+				(&stream).write_all(&line).map_err(transport_error)?;
+
+				let mut line = String::new();
+				BufReader::new(&stream).read_line(&mut line).map_err(transport_error)?;
+				serde_json::from_str(&line).map_err(transport_error)?
+			},
+			Endpoint::Http { url, token } => ureq::post(url)
+				.timeout(TIMEOUT)
+				.set("Authorization", &format!("Bearer {}", token))
+				.send_json(request)
+				.map_err(transport_error)?
+				.into_json()
+				.map_err(transport_error)?,
+		};
+		match response {
+			Response::Error(e) => Err(transport_error(e)),
+			response => Ok(response),
+		}
+	}
+
+	fn public_keys<P>(&self, id: KeyTypeId, scheme: Scheme) -> Result<Vec<P>, Error>
+	where
+		P: for<'a> TryFrom<&'a [u8]>,
+	{
+		match self.call(&Request::PublicKeys { key_type: key_type_name(id), scheme })? {
+			Response::Keys(keys) =>
+				Ok(keys.iter().filter_map(|key| P::try_from(&key[..]).ok()).collect()),
+			response => Err(unexpected(response)),
+		}
+	}
+}
+
+impl SyncCryptoStore for RemoteKeystore {
+	fn sr25519_public_keys(&self, id: KeyTypeId) -> Vec<sr25519::Public> {
+		self.public_keys(id, Scheme::Sr25519).unwrap_or_default()
+	}
+
+	fn sr25519_generate_new(
+		&self,
+		_id: KeyTypeId,
+		_seed: Option<&str>,
+	) -> Result<sr25519::Public, Error> {
+		Err(read_only())
+	}
+
+	fn ed25519_public_keys(&self, id: KeyTypeId) -> Vec<ed25519::Public> {
+		self.public_keys(id, Scheme::Ed25519).unwrap_or_default()
+	}
+
+	fn ed25519_generate_new(
+		&self,
+		_id: KeyTypeId,
+		_seed: Option<&str>,
+	) -> Result<ed25519::Public, Error> {
+		Err(read_only())
+	}
+
+	fn ecdsa_public_keys(&self, id: KeyTypeId) -> Vec<ecdsa::Public> {
+		self.public_keys(id, Scheme::Ecdsa).unwrap_or_default()
+	}
+
+	fn ecdsa_generate_new(
+		&self,
+		_id: KeyTypeId,
+		_seed: Option<&str>,
+	) -> Result<ecdsa::Public, Error> {
+		Err(read_only())
+	}
+
+	fn insert_unknown(&self, _id: KeyTypeId, _suri: &str, _public: &[u8]) -> Result<(), ()> {
+		Err(())
+	}
+
+	fn supported_keys(
+		&self,
+		id: KeyTypeId,
+		keys: Vec<CryptoTypePublicPair>,
+	) -> Result<Vec<CryptoTypePublicPair>, Error> {
+		let held = SyncCryptoStore::keys(self, id)?;
+		Ok(keys.into_iter().filter(|key| held.contains(key)).collect())
+	}
+
+	fn keys(&self, id: KeyTypeId) -> Result<Vec<CryptoTypePublicPair>, Error> {
+		let mut keys = Vec::new();
+		for scheme in Scheme::ALL {
+			let public = self.public_keys::<Vec<u8>>(id, scheme)?;
+			let id = scheme.crypto_id();
+			keys.extend(public.into_iter().map(|key| CryptoTypePublicPair(id, key)));
+		}
+		Ok(keys)
+	}
+
+	fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
+		let keys = public_keys
+			.iter()
+			.map(|(public, id)| (Bytes(public.clone()), key_type_name(*id)))
+			.collect();
+		matches!(self.call(&Request::HasKeys { keys }), Ok(Response::Bool(true)))
+	}
+
+	fn sign_with(
+		&self,
+		id: KeyTypeId,
+		key: &CryptoTypePublicPair,
+		msg: &[u8],
+	) -> Result<Option<Vec<u8>>, Error> {
+		let scheme = Scheme::from_crypto_id(key.0).ok_or(Error::KeyNotSupported(id))?;
+		let request = Request::Sign {
+			key_type: key_type_name(id),
+			scheme,
+			public: Bytes(key.1.clone()),
+			message: Bytes(msg.to_vec()),
+		};
+		match self.call(&request)? {
+			Response::Signature(signature) => Ok(signature.map(|signature| signature.0)),
+			response => Err(unexpected(response)),
+		}
+	}
+
+	fn sr25519_vrf_sign(
+		&self,
+		_key_type: KeyTypeId,
+		_public: &sr25519::Public,
+		_transcript_data: VRFTranscriptData,
+	) -> Result<Option<VRFSignature>, Error> {
+		Err(Error::Unavailable)
+	}
+
+	fn ecdsa_sign_prehashed(
+		&self,
+		id: KeyTypeId,
+		public: &ecdsa::Public,
+		msg: &[u8; 32],
+	) -> Result<Option<ecdsa::Signature>, Error> {
+		let request = Request::SignPrehashed {
+			key_type: key_type_name(id),
+			public: Bytes(public.0.to_vec()),
+			message: Bytes(msg.to_vec()),
+		};
+		match self.call(&request)? {
+			Response::Signature(None) => Ok(None),
+			Response::Signature(Some(signature)) => ecdsa::Signature::try_from(&signature[..])
+				.map(Some)
+				.map_err(|()| transport_error("invalid signature")),
+			response => Err(unexpected(response)),
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl CryptoStore for RemoteKeystore {
+	async fn sr25519_public_keys(&self, id: KeyTypeId) -> Vec<sr25519::Public> {
+		SyncCryptoStore::sr25519_public_keys(self, id)
+	}
+
+	async fn sr25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> Result<sr25519::Public, Error> {
+		SyncCryptoStore::sr25519_generate_new(self, id, seed)
+	}
+
+	async fn ed25519_public_keys(&self, id: KeyTypeId) -> Vec<ed25519::Public> {
+		SyncCryptoStore::ed25519_public_keys(self, id)
+	}
+
+	async fn ed25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> Result<ed25519::Public, Error> {
+		SyncCryptoStore::ed25519_generate_new(self, id, seed)
+	}
+
+	async fn ecdsa_public_keys(&self, id: KeyTypeId) -> Vec<ecdsa::Public> {
+		SyncCryptoStore::ecdsa_public_keys(self, id)
+	}
+
+	async fn ecdsa_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> Result<ecdsa::Public, Error> {
+		SyncCryptoStore::ecdsa_generate_new(self, id, seed)
+	}
+
+	async fn insert_unknown(&self, id: KeyTypeId, suri: &str, public: &[u8]) -> Result<(), ()> {
+		SyncCryptoStore::insert_unknown(self, id, suri, public)
+	}
+
+	async fn supported_keys(
+		&self,
+		id: KeyTypeId,
+		keys: Vec<CryptoTypePublicPair>,
+	) -> Result<Vec<CryptoTypePublicPair>, Error> {
+		SyncCryptoStore::supported_keys(self, id, keys)
+	}
+
+	async fn keys(&self, id: KeyTypeId) -> Result<Vec<CryptoTypePublicPair>, Error> {
+		SyncCryptoStore::keys(self, id)
+	}
+
+	async fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
+		SyncCryptoStore::has_keys(self, public_keys)
+	}
+
+	async fn sign_with(
+		&self,
+		id: KeyTypeId,
+		key: &CryptoTypePublicPair,
+		msg: &[u8],
+	) -> Result<Option<Vec<u8>>, Error> {
+		SyncCryptoStore::sign_with(self, id, key, msg)
+	}
+
+	async fn sr25519_vrf_sign(
+		&self,
+		key_type: KeyTypeId,
+		public: &sr25519::Public,
+		transcript_data: VRFTranscriptData,
+	) -> Result<Option<VRFSignature>, Error> {
+		SyncCryptoStore::sr25519_vrf_sign(self, key_type, public, transcript_data)
+	}
+
+	async fn ecdsa_sign_prehashed(
+		&self,
+		id: KeyTypeId,
+		public: &ecdsa::Public,
+		msg: &[u8; 32],
+	) -> Result<Option<ecdsa::Signature>, Error> {
+		SyncCryptoStore::ecdsa_sign_prehashed(self, id, public, msg)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::{crypto::key_types::AURA, Pair};
+	use sp_keystore::testing::KeyStore;
+	use std::{os::unix::net::UnixListener, sync::Arc, thread};
+
+	/// A remote keystore talking to a signer with a fresh in-memory keystore.
+	fn remote(name: &str) -> (RemoteKeystore, Arc<KeyStore>) {
+		let name = format!("remote-signer-{}-{}", std::process::id(), name);
+		let path = std::env::temp_dir().join(name);
+		let _ = std::fs::remove_file(&path);
+		let listener = UnixListener::bind(&path).unwrap();
+		let store = Arc::new(KeyStore::new());
+		let signer = store.clone();
+		thread::spawn(move || serve_unix(listener, signer));
+
+		let uri = format!("unix://{}", path.display());
+		(RemoteKeystore::new(&uri, None).unwrap(), store)
+	}
+
+	/// The URI of a signer listening for HTTP requests carrying `token`, and its keystore.
+	fn http_signer(token: &str) -> (String, Arc<KeyStore>) {
+		let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+		let uri = format!("http://{}", server.server_addr());
+		let store = Arc::new(KeyStore::new());
+		let (signer, token) = (store.clone(), token.to_string());
+		thread::spawn(move || serve_http(server, signer, token));
+
+		(uri, store)
+	}
+
+	#[test]
+	fn signs_with_remote_key() {
+		let (remote, store) = remote("sign");
+		let public = SyncCryptoStore::sr25519_generate_new(&*store, AURA, Some("//Alice")).unwrap();
+		let key = CryptoTypePublicPair(sr25519::CRYPTO_ID, public.0.to_vec());
+
+		let signature = SyncCryptoStore::sign_with(&remote, AURA, &key, b"block").unwrap().unwrap();
+
+		let signature = sr25519::Signature::try_from(&signature[..]).unwrap();
+		assert!(sr25519::Pair::verify(&signature, b"block", &public));
+	}
+
+	#[test]
+	fn lists_remote_keys() {
+		let (remote, store) = remote("keys");
+
+		let public = SyncCryptoStore::ed25519_generate_new(&*store, AURA, None).unwrap();
+
+		assert_eq!(SyncCryptoStore::ed25519_public_keys(&remote, AURA), vec![public]);
+		assert!(SyncCryptoStore::has_keys(&remote, &[(public.0.to_vec(), AURA)]));
+		assert!(!SyncCryptoStore::has_keys(&remote, &[(vec![0; 32], AURA)]));
+	}
+
+	#[test]
+	fn missing_key_signs_nothing() {
+		let (remote, _) = remote("missing");
+		let key = CryptoTypePublicPair(sr25519::CRYPTO_ID, vec![0; 32]);
+
+		assert_eq!(SyncCryptoStore::sign_with(&remote, AURA, &key, b"block").unwrap(), None);
+	}
+
+	#[test]
+	fn keys_cannot_be_added_through_the_node() {
+		let (remote, store) = remote("add");
+		let public = sr25519::Pair::from_string("//Alice", None).unwrap().public();
+
+		assert!(SyncCryptoStore::sr25519_generate_new(&remote, AURA, None).is_err());
+		assert!(SyncCryptoStore::insert_unknown(&remote, AURA, "//Alice", &public.0).is_err());
+		assert!(SyncCryptoStore::sr25519_public_keys(&*store, AURA).is_empty());
+	}
+
+	#[test]
+	fn http_signer_requires_its_token() {
+		let (uri, store) = http_signer("secret");
+		let public = SyncCryptoStore::sr25519_generate_new(&*store, AURA, Some("//Alice")).unwrap();
+		let key = CryptoTypePublicPair(sr25519::CRYPTO_ID, public.0.to_vec());
+
+		let remote = RemoteKeystore::new(&uri, Some("secret".into())).unwrap();
+		assert!(SyncCryptoStore::sign_with(&remote, AURA, &key, b"block").unwrap().is_some());
+
+		let remote = RemoteKeystore::new(&uri, Some("guess".into())).unwrap();
+		assert!(SyncCryptoStore::sign_with(&remote, AURA, &key, b"block").is_err());
+	}
+
+	#[test]
+	fn rejects_unknown_uri_scheme() {
+		assert!(RemoteKeystore::new("tcp://127.0.0.1:9955", None).is_err());
+	}
+
+	#[test]
+	fn http_takes_a_token_and_unix_none() {
+		assert!(RemoteKeystore::new("http://127.0.0.1:9955", None).is_err());
+		assert!(RemoteKeystore::new("unix:///run/signer.sock", Some("secret".into())).is_err());
+	}
+}
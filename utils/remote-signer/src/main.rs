@@ -0,0 +1,85 @@
+//! Reference signer for nodes started with `--keystore-uri`.
+//!
+//! The signer serves the keys of a regular keystore directory, as created by `node-template key
+//! insert`, over a Unix socket or HTTP. Run it as a separate user, so that the node cannot read
+//! the keys, and only expose it to the node: the Unix socket is created accessible to the
+//! signer's user and group, which the node's user should belong to. The HTTP server only answers
+//! requests carrying the token of `--http-token-file`, and should still listen on a loopback or
+//! private address, as requests and responses are not encrypted.
+
+use clap::Parser;
+use sc_keystore::LocalKeystore;
+use sp_core::crypto::SecretString;
+use std::{
+	fs,
+	net::SocketAddr,
+	os::unix::{fs::PermissionsExt, net::UnixListener},
+	path::PathBuf,
+	sync::Arc,
+};
+
+/// Sign for a node, keeping its keys out of the node's process.
+#[derive(Debug, Parser)]
+#[clap(name = "remote-signer")]
+struct Cli {
+	/// Keystore directory holding the keys.
+	#[clap(long, value_name = "PATH")]
+	keystore_path: PathBuf,
+
+	/// File holding the password of the keystore, if it is encrypted.
+	#[clap(long, value_name = "PATH")]
+	password_filename: Option<PathBuf>,
+
+	/// Listen on the Unix socket at PATH; the node connects with `--keystore-uri unix://PATH`.
+	#[clap(long, value_name = "PATH", required_unless_present = "http", conflicts_with = "http")]
+	unix: Option<PathBuf>,
+
+	/// Listen for HTTP requests on ADDR; the node connects with `--keystore-uri http://ADDR`.
+	#[clap(long, value_name = "ADDR", requires = "http_token_file")]
+	http: Option<SocketAddr>,
+
+	/// File holding the token every HTTP request must carry; the node passes the same file to
+	/// `--keystore-token-file`.
+	#[clap(long, value_name = "PATH", requires = "http")]
+	http_token_file: Option<PathBuf>,
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+	let password = cli
+		.password_filename
+		.map(|path| {
+			fs::read_to_string(&path)
+				.map(SecretString::new)
+				.map_err(|e| format!("reading {}: {}", path.display(), e))
+		})
+		.transpose()?;
+	let keystore = LocalKeystore::open(&cli.keystore_path, password)
+		.map_err(|e| format!("opening {}: {}", cli.keystore_path.display(), e))?;
+	let keystore = Arc::new(keystore);
+
+	if let Some(addr) = cli.http {
+		let path = cli.http_token_file.expect("--http requires --http-token-file; qed");
+		let token = remote_signer::read_token(&path)?;
+		let server = tiny_http::Server::http(addr).map_err(|e| format!("binding {}: {}", addr, e))?;
+		eprintln!("Signing for requests to http://{}", addr);
+		remote_signer::serve_http(server, keystore, token);
+		return Ok(())
+	}
+
+	let path = cli.unix.expect("either --unix or --http is required; qed");
+	// A socket left over by a previous run.
+	let _ = fs::remove_file(&path);
+	let listener =
+		UnixListener::bind(&path).map_err(|e| format!("binding {}: {}", path.display(), e))?;
+	fs::set_permissions(&path, fs::Permissions::from_mode(0o660))
+		.map_err(|e| format!("restricting {}: {}", path.display(), e))?;
+	eprintln!("Signing for connections to unix://{}", path.display());
+	remote_signer::serve_unix(listener, keystore).map_err(|e| e.to_string())
+}
+
+fn main() {
+	if let Err(e) = run(Cli::parse()) {
+		eprintln!("Signer failed: {}", e);
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,122 @@
+//! The signer side of the protocol, used by the `remote-signer` binary.
+
+use crate::{key_type_id, Request, Response, Scheme};
+use sp_core::{crypto::CryptoTypePublicPair, ecdsa, Bytes};
+use sp_keystore::SyncCryptoStore;
+use std::{
+	io::{self, BufRead, BufReader, Read, Write},
+	os::unix::net::UnixListener,
+	sync::Arc,
+	thread,
+};
+
+/// Carries out `request` with the keys of `store`.
+pub fn respond(store: &dyn SyncCryptoStore, request: Request) -> Response {
+	fn keys<P: AsRef<[u8]>>(keys: Vec<P>) -> Response {
+		Response::Keys(keys.iter().map(|key| Bytes(key.as_ref().to_vec())).collect())
+	}
+
+	let result = (|| -> Result<Response, String> {
+		Ok(match request {
+			Request::PublicKeys { key_type, scheme } => {
+				let id = key_type_id(&key_type)?;
+				match scheme {
+					Scheme::Sr25519 => keys(store.sr25519_public_keys(id)),
+					Scheme::Ed25519 => keys(store.ed25519_public_keys(id)),
+					Scheme::Ecdsa => keys(store.ecdsa_public_keys(id)),
+				}
+			},
+			Request::HasKeys { keys } => {
+				let keys = keys
+					.into_iter()
+					.map(|(public, key_type)| Ok((public.0, key_type_id(&key_type)?)))
+					.collect::<Result<Vec<_>, String>>()?;
+				Response::Bool(store.has_keys(&keys))
+			},
+			Request::Sign { key_type, scheme, public, message } => {
+				let key = CryptoTypePublicPair(scheme.crypto_id(), public.0);
+				let signature = store
+					.sign_with(key_type_id(&key_type)?, &key, &message)
+					.map_err(|e| e.to_string())?;
+				Response::Signature(signature.map(Bytes))
+			},
+			Request::SignPrehashed { key_type, public, message } => {
+				let public = ecdsa::Public::try_from(&public[..])
+					.map_err(|()| "invalid ECDSA public key".to_string())?;
+				let message: [u8; 32] =
+					message.0.try_into().map_err(|_| "expected a 32-byte hash".to_string())?;
+				let signature = store
+					.ecdsa_sign_prehashed(key_type_id(&key_type)?, &public, &message)
+					.map_err(|e| e.to_string())?;
+				Response::Signature(signature.map(|signature| Bytes(signature.0.to_vec())))
+			},
+		})
+	})();
+	result.unwrap_or_else(Response::Error)
+}
+
+/// Answers the requests of every connection to `listener`, one line each, with `store`.
+pub fn serve_unix(listener: UnixListener, store: Arc<dyn SyncCryptoStore>) -> io::Result<()> {
+	for stream in listener.incoming() {
+		let stream = stream?;
+		let store = store.clone();
+		thread::spawn(move || {
+			let mut writer = &stream;
+			for line in BufReader::new(&stream).lines() {
+				let response = match line.map(|line| serde_json::from_str(&line)) {
+					Ok(Ok(request)) => respond(&*store, request),
+					Ok(Err(e)) => Response::Error(format!("invalid request: {}", e)),
+					Err(_) => break,
+				};
+				let mut response = serde_json::to_vec(&response).expect("responses serialize; qed");
+				response.push(b'\n');
+				if writer.write_all(&response).is_err() {
+					break
+				}
+			}
+		});
+	}
+	Ok(())
+}
+
+/// Answers the `POST /` requests received by `server` with `store`.
+///
+/// Requests must carry `token` in an `Authorization: Bearer <token>` header, and are answered
+/// with status 401 otherwise.
+pub fn serve_http(server: tiny_http::Server, store: Arc<dyn SyncCryptoStore>, token: String) {
+	let expected = format!("Bearer {}", token);
+	for mut request in server.incoming_requests() {
+		let authorization = request
+			.headers()
+			.iter()
+			.find(|header| header.field.equiv("Authorization"))
+			.map(|header| header.value.as_str());
+		if !authorization.map_or(false, |value| constant_time_eq(value, &expected)) {
+			let _ = request.respond(tiny_http::Response::empty(401));
+			continue
+		}
+		if *request.method() != tiny_http::Method::Post {
+			let _ = request.respond(tiny_http::Response::empty(405));
+			continue
+		}
+
+		let mut body = String::new();
+		let response = match request.as_reader().read_to_string(&mut body) {
+			Ok(_) => match serde_json::from_str(&body) {
+				Ok(call) => respond(&*store, call),
+				Err(e) => Response::Error(format!("invalid request: {}", e)),
+			},
+			Err(e) => Response::Error(format!("failed to read the request: {}", e)),
+		};
+		let response = serde_json::to_string(&response).expect("responses serialize; qed");
+		let content_type = tiny_http::Header::from_bytes("Content-Type", "application/json")
+			.expect("the header is valid; qed");
+		let response = tiny_http::Response::from_string(response).with_header(content_type);
+		let _ = request.respond(response);
+	}
+}
+
+/// Whether `a` equals `b`, taking the same time for every `a` of the length of `b`.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+	a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}